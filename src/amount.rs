@@ -0,0 +1,102 @@
+#[cfg(feature = "decimal")]
+use rust_decimal::{Decimal, RoundingStrategy};
+
+use num_traits::{NumCast, ToPrimitive};
+
+/// Amount of transactions and balances used in the public API
+/// `f64` by default, `rust_decimal::Decimal` with `decimal` feature to keep the values exact also at the edges
+/// Internally the processor always calculates on integers scaled by `10^decimal_places`,
+/// so also `Decimal` amounts are rounded to the precision of the processor
+#[cfg(not(feature = "decimal"))]
+pub type Amount = f64;
+
+/// Amount of transactions and balances used in the public API
+/// `f64` by default, `rust_decimal::Decimal` with `decimal` feature to keep the values exact also at the edges
+/// Internally the processor always calculates on integers scaled by `10^decimal_places`,
+/// so also `Decimal` amounts are rounded to the precision of the processor
+#[cfg(feature = "decimal")]
+pub type Amount = Decimal;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+/// Rounding of amounts having more decimal places than the precision of the processor
+pub enum RoundingMode {
+    /// Ties are rounded away from zero, e.g. 0.00005 becomes 0.0001 with 4 decimal places
+    #[default]
+    HalfUp,
+    /// Ties are rounded to the even digit (banker's rounding), e.g. 0.00005 becomes 0.0000 with 4 decimal places
+    HalfEven,
+}
+
+impl RoundingMode {
+    #[cfg(not(feature = "decimal"))]
+    fn round(self, v: f64) -> f64 {
+        match self {
+            RoundingMode::HalfUp => v.round(),
+            RoundingMode::HalfEven => v.round_ties_even(),
+        }
+    }
+
+    #[cfg(feature = "decimal")]
+    fn strategy(self) -> RoundingStrategy {
+        match self {
+            RoundingMode::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            RoundingMode::HalfEven => RoundingStrategy::MidpointNearestEven,
+        }
+    }
+}
+
+/// Returns the amount as integer scaled by `10^decimal_places`, None if it does not fit in the integer type
+#[cfg(not(feature = "decimal"))]
+pub(crate) fn to_scaled<I: NumCast>(
+    amount: Amount,
+    decimal_places: u32,
+    rounding_mode: RoundingMode,
+) -> Option<I> {
+    I::from(rounding_mode.round(amount * 10f64.powi(decimal_places as i32)))
+}
+
+/// Returns the amount as integer scaled by `10^decimal_places`, None if it does not fit in the integer type
+#[cfg(feature = "decimal")]
+pub(crate) fn to_scaled<I: NumCast>(
+    amount: Amount,
+    decimal_places: u32,
+    rounding_mode: RoundingMode,
+) -> Option<I> {
+    let rounded = amount.round_dp_with_strategy(decimal_places, rounding_mode.strategy());
+    let scale = 10_i128.checked_pow(decimal_places - rounded.scale())?;
+    I::from(rounded.mantissa().checked_mul(scale)?)
+}
+
+/// Returns the amount represented by the integer scaled by `10^decimal_places`
+#[cfg(not(feature = "decimal"))]
+pub(crate) fn from_scaled<I: ToPrimitive>(v: I, decimal_places: u32) -> Amount {
+    v.to_f64().unwrap_or(f64::NAN) / 10f64.powi(decimal_places as i32)
+}
+
+/// Returns the amount represented by the integer scaled by `10^decimal_places`
+/// Saturates at `Decimal::MAX`/`Decimal::MIN` if the value is out of the `Decimal` range
+#[cfg(feature = "decimal")]
+pub(crate) fn from_scaled<I: ToPrimitive>(v: I, decimal_places: u32) -> Amount {
+    let v = v.to_i128().unwrap_or_default();
+    Decimal::try_from_i128_with_scale(v, decimal_places)
+        .map(|amount| amount.normalize())
+        .unwrap_or(if v < 0 { Decimal::MIN } else { Decimal::MAX })
+}
+
+/// Parses the amount written in tests, so the same tests run with `f64` and `Decimal` amounts
+#[cfg(test)]
+pub(crate) fn amount(value: &str) -> Amount {
+    value.parse().unwrap()
+}
+
+/// Returns false for infinite and NaN amounts
+#[cfg(not(feature = "decimal"))]
+pub(crate) fn is_finite(amount: Amount) -> bool {
+    amount.is_finite()
+}
+
+/// `Decimal` amounts are always finite
+#[cfg(feature = "decimal")]
+pub(crate) fn is_finite(_amount: Amount) -> bool {
+    true
+}
@@ -0,0 +1,302 @@
+use std::collections::BTreeMap;
+
+use num_traits::Zero;
+
+use crate::amount::{self, Amount, RoundingMode};
+use crate::models::{ClientId, ClientSummary};
+use crate::precision::DECIMAL_PLACES;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Kind of the change of the client between two summaries
+pub enum ClientChange {
+    /// Client is only in the second summary
+    Added,
+    /// Client is only in the first summary
+    Removed,
+    /// Client is in both summaries with different values
+    Changed,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Difference of a single client between two summaries, amounts are `after - before`
+/// Missing client is treated as account with zero balances
+pub struct ClientSummaryDiff {
+    pub client: ClientId,
+    pub change: ClientChange,
+    pub available: Amount,
+    pub held: Amount,
+    pub total: Amount,
+    /// Lock status in the first summary, None if client is missing
+    pub locked_before: Option<bool>,
+    /// Lock status in the second summary, None if client is missing
+    pub locked_after: Option<bool>,
+}
+
+/// Amounts are compared as integers with the default precision to ignore f64 representation differences
+/// None for amounts that cannot be represented with it (NaN or out of range)
+fn to_scaled(v: Amount) -> Option<i128> {
+    amount::to_scaled(v, DECIMAL_PLACES, RoundingMode::HalfUp)
+}
+
+fn from_scaled(v: i128) -> Amount {
+    amount::from_scaled(v, DECIMAL_PLACES)
+}
+
+/// Returns differences of all clients that were added, removed or changed between summaries, sorted by client id
+/// Fails with ids of the clients having amounts that cannot be represented with the default precision (NaN or out of range)
+pub fn diff_summaries(
+    before: &[ClientSummary],
+    after: &[ClientSummary],
+) -> Result<Vec<ClientSummaryDiff>, Vec<ClientId>> {
+    let mut clients: BTreeMap<ClientId, (Option<&ClientSummary>, Option<&ClientSummary>)> =
+        BTreeMap::new();
+    for summary in before {
+        clients.entry(summary.client).or_default().0 = Some(summary);
+    }
+    for summary in after {
+        clients.entry(summary.client).or_default().1 = Some(summary);
+    }
+
+    let mut diffs = vec![];
+    let mut unrepresentable = vec![];
+    for (client, (before, after)) in clients {
+        let delta = |amount: fn(&ClientSummary) -> Amount| {
+            to_scaled(after.map_or(Amount::zero(), amount))?
+                .checked_sub(to_scaled(before.map_or(Amount::zero(), amount))?)
+        };
+        let (Some(available), Some(held), Some(total)) = (
+            delta(|s| s.available),
+            delta(|s| s.held),
+            delta(|s| s.total),
+        ) else {
+            unrepresentable.push(client);
+            continue;
+        };
+        let locked_before = before.map(|s| s.locked);
+        let locked_after = after.map(|s| s.locked);
+
+        let change = match (before, after) {
+            (None, _) => ClientChange::Added,
+            (_, None) => ClientChange::Removed,
+            _ if available != 0 || held != 0 || total != 0 || locked_before != locked_after => {
+                ClientChange::Changed
+            }
+            _ => continue,
+        };
+        diffs.push(ClientSummaryDiff {
+            client,
+            change,
+            available: from_scaled(available),
+            held: from_scaled(held),
+            total: from_scaled(total),
+            locked_before,
+            locked_after,
+        });
+    }
+    if unrepresentable.is_empty() {
+        Ok(diffs)
+    } else {
+        Err(unrepresentable)
+    }
+}
+
+/// Checks that total equals available plus held in every summary (with the default precision),
+/// e.g. to detect corrupted summaries produced externally
+/// Returns ids of the inconsistent clients in the order of the summaries
+pub fn verify_summary(summaries: &[ClientSummary]) -> Result<(), Vec<ClientId>> {
+    let inconsistent: Vec<ClientId> = summaries
+        .iter()
+        .filter(|s| !is_consistent(s))
+        .map(|s| s.client)
+        .collect();
+    if inconsistent.is_empty() {
+        Ok(())
+    } else {
+        Err(inconsistent)
+    }
+}
+
+/// Returns true if total equals available plus held with the default precision
+/// Amounts that cannot be represented with it (NaN or out of range) are inconsistent
+pub(crate) fn is_consistent(summary: &ClientSummary) -> bool {
+    let scaled = |v: Amount| amount::to_scaled::<i64>(v, DECIMAL_PLACES, RoundingMode::HalfUp);
+    match (
+        scaled(summary.available),
+        scaled(summary.held),
+        scaled(summary.total),
+    ) {
+        (Some(available), Some(held), Some(total)) => available.checked_add(held) == Some(total),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amount::amount;
+
+    #[test]
+    fn verify_summary_should_return_inconsistent_clients() {
+        let consistent = vec![
+            ClientSummary::new(1, amount("0.1"), amount("0.2"), false),
+            ClientSummary::new(2, amount("-5.0"), amount("10.0"), true),
+            ClientSummary {
+                client: 3,
+                available: amount("0.1"),
+                held: amount("0.2"),
+                total: amount("0.3"),
+                locked: false,
+            },
+        ];
+        assert_eq!(verify_summary(&consistent), Ok(()));
+
+        let mut corrupted = consistent.clone();
+        corrupted[1].total = amount("15.0");
+        assert_eq!(verify_summary(&corrupted), Err(vec![2]));
+    }
+
+    // `Decimal` amounts cannot be NaN
+    #[cfg(not(feature = "decimal"))]
+    #[test]
+    fn verify_summary_with_nan_total_should_be_inconsistent() {
+        let mut summary = ClientSummary::new(1, amount("0.0"), amount("0.0"), false);
+        summary.total = f64::NAN;
+
+        assert_eq!(verify_summary(&[summary]), Err(vec![1]));
+    }
+
+    #[test]
+    fn verify_summary_with_overflowing_amounts_should_be_inconsistent() {
+        let summaries = [
+            ClientSummary::new(
+                1,
+                amount("900000000000000"),
+                amount("900000000000000"),
+                false,
+            ),
+            ClientSummary::new(2, amount("1000000000000000"), amount("0.0"), false),
+        ];
+
+        assert_eq!(verify_summary(&summaries), Err(vec![1, 2]));
+    }
+
+    #[test]
+    fn diff_should_report_added_changed_and_removed_clients() {
+        let before = vec![
+            ClientSummary {
+                client: 1,
+                available: amount("10.0"),
+                held: amount("0.0"),
+                total: amount("10.0"),
+                locked: false,
+            },
+            ClientSummary {
+                client: 2,
+                available: amount("0.1"),
+                held: amount("0.2"),
+                total: amount("0.3"),
+                locked: false,
+            },
+            ClientSummary {
+                client: 3,
+                available: amount("7.5"),
+                held: amount("0.0"),
+                total: amount("7.5"),
+                locked: false,
+            },
+        ];
+        let after = vec![
+            ClientSummary {
+                client: 1,
+                available: amount("10.0"),
+                held: amount("0.0"),
+                total: amount("10.0"),
+                locked: false,
+            },
+            ClientSummary {
+                client: 2,
+                available: amount("0.3"),
+                held: amount("0.0"),
+                total: amount("0.3"),
+                locked: true,
+            },
+            ClientSummary {
+                client: 4,
+                available: amount("1.0"),
+                held: amount("0.0"),
+                total: amount("1.0"),
+                locked: false,
+            },
+        ];
+
+        assert_eq!(
+            diff_summaries(&before, &after).unwrap(),
+            vec![
+                ClientSummaryDiff {
+                    client: 2,
+                    change: ClientChange::Changed,
+                    available: amount("0.2"),
+                    held: amount("-0.2"),
+                    total: amount("0.0"),
+                    locked_before: Some(false),
+                    locked_after: Some(true),
+                },
+                ClientSummaryDiff {
+                    client: 3,
+                    change: ClientChange::Removed,
+                    available: amount("-7.5"),
+                    held: amount("0.0"),
+                    total: amount("-7.5"),
+                    locked_before: Some(false),
+                    locked_after: None,
+                },
+                ClientSummaryDiff {
+                    client: 4,
+                    change: ClientChange::Added,
+                    available: amount("1.0"),
+                    held: amount("0.0"),
+                    total: amount("1.0"),
+                    locked_before: None,
+                    locked_after: Some(false),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_of_amounts_at_the_bounds_should_not_overflow() {
+        let before = [ClientSummary::new(
+            1,
+            amount("-900000000000000"),
+            amount("0.0"),
+            false,
+        )];
+        let after = [ClientSummary::new(
+            1,
+            amount("900000000000000"),
+            amount("0.0"),
+            false,
+        )];
+
+        let diff = diff_summaries(&before, &after).unwrap();
+
+        assert_eq!(diff[0].available, amount("1800000000000000"));
+        assert_eq!(diff[0].total, amount("1800000000000000"));
+    }
+
+    // `Decimal` amounts cannot be NaN and always fit in the scaled integer
+    #[cfg(not(feature = "decimal"))]
+    #[test]
+    fn diff_with_unrepresentable_amounts_should_fail() {
+        let before = [
+            ClientSummary::new(1, amount("1.0"), amount("0.0"), false),
+            ClientSummary::new(2, amount("1.0"), amount("0.0"), false),
+            ClientSummary::new(3, amount("1.0"), amount("0.0"), false),
+        ];
+        let mut after = before.clone();
+        after[0].available = f64::NAN;
+        after[2].total = 1e35;
+
+        assert_eq!(diff_summaries(&before, &after), Err(vec![1, 3]));
+    }
+}
@@ -0,0 +1,44 @@
+use std::error::Error;
+use std::io;
+
+use csv::Position;
+
+/// Typed error of processing the transactions file, see `try_process_transactions`
+#[derive(Debug, thiserror::Error)]
+pub enum ProcessFileError {
+    /// The input file cannot be opened or read
+    #[error("Failed to read input file")]
+    Io(#[from] io::Error),
+
+    /// The record is malformed (e.g. unknown transaction type or invalid amount), processing is aborted
+    #[error("Failed to parse record at line {line}: {source}")]
+    Parse {
+        line: u64,
+        #[source]
+        source: Box<dyn Error + Send + Sync>,
+    },
+
+    /// The record is not valid UTF-8
+    #[error("Invalid UTF-8 in record at line {line}")]
+    Encoding { line: u64 },
+
+    /// The summary cannot be rendered after processing, an internal error not caused by the input
+    #[error("Failed to render summary")]
+    Output(#[source] Box<dyn Error + Send + Sync>),
+}
+
+impl From<csv::Error> for ProcessFileError {
+    fn from(err: csv::Error) -> Self {
+        let line = err.position().map_or(0, Position::line);
+        if err.is_io_error() {
+            ProcessFileError::Io(err.into())
+        } else if matches!(err.kind(), csv::ErrorKind::Utf8 { .. }) {
+            ProcessFileError::Encoding { line }
+        } else {
+            ProcessFileError::Parse {
+                line,
+                source: Box::new(err),
+            }
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+
+use crate::models::{ClientId, TransactionId};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Describes status of the transaction in user history
+pub enum TransactionStatus {
+    /// Transaction was successful and is valid, the founds are in available
+    /// After a partial chargeback the rest of the amount stays processed and can be disputed
+    Processed,
+    /// Transaction is under dispute, the disputed founds are in held (withdrawals stay deducted from available)
+    /// For partial disputes the rest of the amount stays processed
+    UnderDispute,
+    /// Whole transaction is charged back, it is ignored in held/total but client account is frozen
+    ChargeBack,
+    /// Opening balance of the client, it is settled so it cannot be disputed
+    Settled,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Details of the deposit, withdrawal or opening balance in client history
+pub struct HistoryRecord<A> {
+    /// Internal integer amount, deposits are positive and withdrawals negative
+    pub amount: A,
+    pub status: TransactionStatus,
+    /// Positive part of the amount under dispute, zero for transactions not under dispute
+    /// Less than the whole amount for partial disputes
+    pub disputed: A,
+    /// Positive part of the amount charged back, the whole amount for charged back transactions
+    pub charged_back: A,
+    /// Number of disputes of the transaction, disputes after the first one re-open a resolved dispute
+    pub disputes: u32,
+    /// Position of the transaction in client history, records are never removed so it reflects the insertion order
+    pub sequence: usize,
+}
+
+/// Storage of the deposits and withdrawals processed by `TransactionsProcessor`
+/// Transaction ids are unique per client, the same id can be used by different clients
+pub trait HistoryStore<A> {
+    fn get(&self, client: ClientId, transaction_id: TransactionId) -> Option<HistoryRecord<A>>;
+
+    fn insert(&mut self, client: ClientId, transaction_id: TransactionId, record: HistoryRecord<A>);
+
+    /// Replaces the record after a dispute, resolve or chargeback, called only for transactions already in the store
+    fn update(&mut self, client: ClientId, transaction_id: TransactionId, record: HistoryRecord<A>);
+
+    /// Returns all the records of the client in any order
+    fn client_records(&self, client: ClientId) -> Vec<(TransactionId, HistoryRecord<A>)>;
+}
+
+/// In memory `HistoryStore` used by default
+pub struct HashMapHistoryStore<A> {
+    records: HashMap<ClientId, HashMap<TransactionId, HistoryRecord<A>>>,
+    /// Initial capacity of the history of each client
+    transactions_per_client: usize,
+}
+
+impl<A> Default for HashMapHistoryStore<A> {
+    fn default() -> Self {
+        Self::with_capacity(0, 0)
+    }
+}
+
+impl<A> HashMapHistoryStore<A> {
+    /// Creates the store pre-sized for the expected number of clients and transactions of each client
+    pub fn with_capacity(expected_clients: usize, expected_transactions_per_client: usize) -> Self {
+        Self {
+            records: HashMap::with_capacity(expected_clients),
+            transactions_per_client: expected_transactions_per_client,
+        }
+    }
+}
+
+impl<A: Copy> HistoryStore<A> for HashMapHistoryStore<A> {
+    fn get(&self, client: ClientId, transaction_id: TransactionId) -> Option<HistoryRecord<A>> {
+        self.records.get(&client)?.get(&transaction_id).copied()
+    }
+
+    fn insert(
+        &mut self,
+        client: ClientId,
+        transaction_id: TransactionId,
+        record: HistoryRecord<A>,
+    ) {
+        self.records
+            .entry(client)
+            .or_insert_with(|| HashMap::with_capacity(self.transactions_per_client))
+            .insert(transaction_id, record);
+    }
+
+    fn update(
+        &mut self,
+        client: ClientId,
+        transaction_id: TransactionId,
+        record: HistoryRecord<A>,
+    ) {
+        if let Some(existing) = self
+            .records
+            .get_mut(&client)
+            .and_then(|records| records.get_mut(&transaction_id))
+        {
+            *existing = record;
+        }
+    }
+
+    fn client_records(&self, client: ClientId) -> Vec<(TransactionId, HistoryRecord<A>)> {
+        self.records
+            .get(&client)
+            .map(|records| {
+                records
+                    .iter()
+                    .map(|(transaction_id, record)| (*transaction_id, *record))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
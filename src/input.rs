@@ -0,0 +1,197 @@
+use std::io::{self, Read};
+
+use anyhow::bail;
+use csv::StringRecord;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+/// Decimal separator used by amounts in the input
+pub enum DecimalSeparator {
+    /// `12.50`
+    #[default]
+    Dot,
+    /// `12,50`, the field has to be quoted as `,` is also the CSV delimiter
+    Comma,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+/// Handling of the input rows with transaction type that cannot be parsed
+pub enum UnknownTypePolicy {
+    /// Processing fails with the error of the row
+    #[default]
+    Abort,
+    /// The row is skipped as if it was not in the input, e.g. a transaction type added in the future
+    Skip,
+}
+
+/// Translates the amount of the record to use `.` as decimal separator, as expected by deserialization
+/// Thousands grouping is not supported, amounts with both `.` and `,` are rejected
+pub(crate) fn normalize_amount(
+    record: StringRecord,
+    amount_index: Option<usize>,
+    separator: DecimalSeparator,
+) -> anyhow::Result<StringRecord> {
+    let Some(amount) = amount_index.and_then(|index| record.get(index)) else {
+        return Ok(record);
+    };
+    match separator {
+        DecimalSeparator::Dot => {
+            if amount.contains(',') {
+                bail!(
+                    "Amount {:?} uses ',' as decimal separator, while '.' is expected",
+                    amount
+                )
+            }
+            Ok(record)
+        }
+        DecimalSeparator::Comma => {
+            if amount.contains('.') || amount.matches(',').count() > 1 {
+                bail!(
+                    "Amount {:?} is ambiguous, thousands grouping is not supported with ',' decimal separator",
+                    amount
+                )
+            }
+            Ok(record
+                .iter()
+                .enumerate()
+                .map(|(index, field)| {
+                    if Some(index) == amount_index {
+                        field.replace(',', ".")
+                    } else {
+                        field.to_string()
+                    }
+                })
+                .collect())
+        }
+    }
+}
+
+/// Input failing as soon as a record has more bytes than `max_record_bytes`, before the CSV reader buffers it
+/// Bytes are counted from the end of the previous record, including delimiters and quotes but not the line terminator
+/// Line terminators inside quoted fields do not end the record
+pub(crate) struct RecordBytesLimit<R> {
+    input: R,
+    max_record_bytes: Option<usize>,
+    record_bytes: usize,
+    in_quotes: bool,
+    line: u64,
+    record_line: u64,
+    exceeded: bool,
+}
+
+impl<R: Read> RecordBytesLimit<R> {
+    pub(crate) fn new(input: R, max_record_bytes: Option<usize>) -> Self {
+        Self {
+            input,
+            max_record_bytes,
+            record_bytes: 0,
+            in_quotes: false,
+            line: 1,
+            record_line: 1,
+            exceeded: false,
+        }
+    }
+
+    fn error(&self, max_record_bytes: usize) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Record at line {} exceeds the limit of {} bytes",
+                self.record_line, max_record_bytes
+            ),
+        )
+    }
+}
+
+impl<R: Read> Read for RecordBytesLimit<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some(max_record_bytes) = self.max_record_bytes else {
+            return self.input.read(buf);
+        };
+        if self.exceeded {
+            return Err(self.error(max_record_bytes));
+        }
+        let read = self.input.read(buf)?;
+        for (index, byte) in buf[..read].iter().enumerate() {
+            match byte {
+                b'"' => self.in_quotes = !self.in_quotes,
+                b'\n' => self.line += 1,
+                _ => {}
+            }
+            if !self.in_quotes && matches!(byte, b'\n' | b'\r') {
+                self.record_bytes = 0;
+                self.record_line = self.line;
+                continue;
+            }
+            self.record_bytes += 1;
+            if self.record_bytes > max_record_bytes {
+                self.exceeded = true;
+                // Records before the long one are still passed to the reader, the error is returned by the next read
+                return if index > 0 {
+                    Ok(index)
+                } else {
+                    Err(self.error(max_record_bytes))
+                };
+            }
+        }
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comma_separator_should_be_translated_to_dot() {
+        let record = StringRecord::from(vec!["deposit", "1", "1", "12,50"]);
+        let normalized = normalize_amount(record, Some(3), DecimalSeparator::Comma).unwrap();
+        assert_eq!(normalized, vec!["deposit", "1", "1", "12.50"]);
+    }
+
+    #[test]
+    fn comma_separator_with_thousands_grouping_should_fail() {
+        let record = StringRecord::from(vec!["deposit", "1", "1", "1.234,50"]);
+        let err = normalize_amount(record, Some(3), DecimalSeparator::Comma).unwrap_err();
+        assert!(err.to_string().contains("thousands grouping"));
+    }
+
+    #[test]
+    fn record_bytes_limit_should_not_pass_more_bytes_of_record_than_the_limit() {
+        let input = "type,client,tx,amount\r\ndeposit,1,1,"
+            .as_bytes()
+            .chain(io::repeat(b'1'));
+        let mut limited = RecordBytesLimit::new(input, Some(100));
+        let mut read = vec![];
+
+        let err = limited.read_to_end(&mut read).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "Record at line 2 exceeds the limit of 100 bytes"
+        );
+        assert_eq!(read.len(), "type,client,tx,amount\r\n".len() + 100);
+    }
+
+    #[test]
+    fn record_bytes_limit_should_count_quoted_line_terminators_as_part_of_record() {
+        let input = "deposit,1,1,\"\n\n\n\n\n\n\"\ndeposit,1,2,1.0\n";
+        let mut read = vec![];
+
+        assert!(RecordBytesLimit::new(input.as_bytes(), Some(18))
+            .read_to_end(&mut read)
+            .is_err());
+        read.clear();
+        RecordBytesLimit::new(input.as_bytes(), Some(20))
+            .read_to_end(&mut read)
+            .unwrap();
+        assert_eq!(read, input.as_bytes());
+    }
+
+    #[test]
+    fn record_without_amount_should_not_change() {
+        let record = StringRecord::from(vec!["dispute", "1", "1"]);
+        let normalized = normalize_amount(record, Some(3), DecimalSeparator::Comma).unwrap();
+        assert_eq!(normalized, vec!["dispute", "1", "1"]);
+    }
+}
@@ -0,0 +1,151 @@
+use std::io::BufRead;
+
+use anyhow::Context;
+use csv::{ReaderBuilder, StringRecord, Trim};
+
+use crate::models::{ClientSummary, Transaction};
+use crate::processor::{TransactionProcessError, TransactionsProcessor};
+
+/// Processes transactions read one line at a time, so the summary can be inspected between the lines
+/// (e.g. in an interactive session), each line is parsed with the header read once at the beginning
+pub struct LineByLineProcessor<R: BufRead> {
+    input: R,
+    headers: StringRecord,
+    processor: TransactionsProcessor,
+}
+
+impl<R: BufRead> LineByLineProcessor<R> {
+    /// Creates the processor reading the header from the first line of the input
+    pub fn new(mut input: R) -> anyhow::Result<Self> {
+        let mut header = String::new();
+        input
+            .read_line(&mut header)
+            .context("Failed to read header line")?;
+        Self::with_header(input, &header)
+    }
+
+    /// Creates the processor for the input without header, using the given header line instead
+    pub fn with_header(input: R, header: &str) -> anyhow::Result<Self> {
+        let headers = parse_line(header)?.context("Header line is empty")?;
+        Ok(Self {
+            input,
+            headers,
+            processor: TransactionsProcessor::default(),
+        })
+    }
+
+    /// Reads and processes the next transaction, empty lines are skipped
+    /// Returns None when there are no more lines, otherwise the result of processing the transaction
+    pub fn process_next(&mut self) -> anyhow::Result<Option<Result<(), TransactionProcessError>>> {
+        loop {
+            let mut line = String::new();
+            let read = self
+                .input
+                .read_line(&mut line)
+                .context("Failed to read line")?;
+            if read == 0 {
+                return Ok(None);
+            }
+            if let Some(record) = parse_line(&line)? {
+                let transaction: Transaction = record
+                    .deserialize(Some(&self.headers))
+                    .context("Failed to deserialize transaction")?;
+                return Ok(Some(self.processor.process(&transaction)));
+            }
+        }
+    }
+
+    /// Returns summary of client accounts after transactions processed so far
+    pub fn summary(&self) -> Vec<ClientSummary> {
+        self.processor.summary()
+    }
+
+    /// Returns the underlying processor
+    pub fn processor(&self) -> &TransactionsProcessor {
+        &self.processor
+    }
+}
+
+/// Parses single CSV line, returns None for an empty line
+fn parse_line(line: &str) -> anyhow::Result<Option<StringRecord>> {
+    ReaderBuilder::new()
+        .trim(Trim::All)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(line.as_bytes())
+        .records()
+        .next()
+        .transpose()
+        .context("Failed to parse line")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::amount::amount;
+
+    #[test]
+    fn summary_should_be_available_between_lines() {
+        let input = Cursor::new(
+            "type, client, tx, amount\n\
+            deposit, 1, 1, 10.0\n\
+            \n\
+            withdrawal, 1, 2, 20.0\n\
+            dispute, 1, 1\n",
+        );
+        let mut processor = LineByLineProcessor::new(input).unwrap();
+        assert_eq!(processor.summary(), vec![]);
+
+        assert_eq!(processor.process_next().unwrap(), Some(Ok(())));
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary {
+                client: 1,
+                available: amount("10.0"),
+                held: amount("0.0"),
+                total: amount("10.0"),
+                locked: false,
+            }]
+        );
+
+        assert_eq!(
+            processor.process_next().unwrap(),
+            Some(Err(TransactionProcessError::NotEnoughFoundsAvailable))
+        );
+
+        assert_eq!(processor.process_next().unwrap(), Some(Ok(())));
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary {
+                client: 1,
+                available: amount("0.0"),
+                held: amount("10.0"),
+                total: amount("10.0"),
+                locked: false,
+            }]
+        );
+
+        assert_eq!(processor.process_next().unwrap(), None);
+    }
+
+    #[test]
+    fn lines_without_header_should_use_given_header() {
+        let input = Cursor::new("deposit, 3, 1, 5.5\n");
+        let mut processor =
+            LineByLineProcessor::with_header(input, "type, client, tx, amount").unwrap();
+
+        assert_eq!(processor.process_next().unwrap(), Some(Ok(())));
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary {
+                client: 3,
+                available: amount("5.5"),
+                held: amount("0.0"),
+                total: amount("5.5"),
+                locked: false,
+            }]
+        );
+    }
+}
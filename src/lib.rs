@@ -1,63 +1,351 @@
-use std::fs::File;
-use std::io::BufReader;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Write};
 use std::path::Path;
 
 use anyhow::Context;
-use csv::{ReaderBuilder, Trim, WriterBuilder};
-use log::{error, info};
+use csv::{Position, ReaderBuilder, StringRecord, Trim};
+use itertools::Itertools;
+use log::info;
 
-use crate::models::{ClientSummary, Transaction};
-use crate::processor::TransactionsProcessor;
+use crate::input::RecordBytesLimit;
 
+pub use crate::amount::{Amount, RoundingMode};
+pub use crate::diff::{diff_summaries, verify_summary, ClientChange, ClientSummaryDiff};
+pub use crate::file_error::ProcessFileError;
+pub use crate::history::{HashMapHistoryStore, HistoryRecord, HistoryStore, TransactionStatus};
+pub use crate::input::{DecimalSeparator, UnknownTypePolicy};
+pub use crate::interactive::LineByLineProcessor;
+pub use crate::merge::merge_summary_csvs;
+pub use crate::metrics::{DisputeCounts, DisputeMetrics, StatusCounts};
+pub use crate::models::{
+    ClientId, ClientSummary, GrandTotals, Timestamp, Transaction, TransactionId, TransactionType,
+    TransactionTypeParseError, TransactionValidationError, INPUT_HEADERS,
+};
+pub use crate::options::ProcessingOptions;
+pub use crate::output::{
+    write_summary_ndjson, AmountFormat, LineTerminator, OutputFormat, QuoteStyle,
+};
+pub use crate::processor::{
+    AmountInt, TransactionProcessError, TransactionRecordView, TransactionsProcessor,
+    TransactionsProcessorBuilder, TxStatusView, OPENING_BALANCE_TRANSACTION_ID,
+};
+pub use crate::rejections::{write_raw_rejections, write_rejections, ProcessingError};
+pub use crate::stepping::{ProcessingDriver, RecordProcessor};
+#[cfg(feature = "testing")]
+pub use crate::testing::generate_transactions;
+
+mod amount;
+mod diff;
+mod file_error;
+mod history;
+mod input;
+mod interactive;
+mod merge;
+mod metrics;
 mod models;
+mod options;
+mod output;
+/// Fixed point representation of amounts used by default by `TransactionsProcessor`
+pub mod precision;
 mod processor;
+mod rejections;
+mod resume;
+mod stepping;
+#[cfg(feature = "testing")]
+mod testing;
+
+/// Result of processing the transactions file
+#[derive(Debug)]
+pub struct ProcessingReport {
+    /// Summary of all client accounts, CSV unless other `OutputFormat` is selected
+    pub summary: String,
+    /// Transactions rejected by the processor in the order of the input file
+    pub rejections: Vec<ProcessingError>,
+    /// True if processing stopped before the end of the input (e.g. after too many rejections)
+    pub truncated: bool,
+    /// Client whose account lock stopped the processing when `ProcessingOptions::halt_on_lock` is enabled
+    pub halted_by: Option<ClientId>,
+}
+
+impl ProcessingReport {
+    /// Returns errors of the rejected transactions grouped by client, in the order of the input file
+    pub fn rejections_by_client(&self) -> HashMap<ClientId, Vec<TransactionProcessError>> {
+        self.rejections
+            .iter()
+            .map(|rejection| (rejection.transaction.client, rejection.error.clone()))
+            .into_group_map()
+    }
+
+    /// Returns the rejected transactions that were skipped because the account was locked, e.g. for audit
+    pub fn locked_account_rejections(&self) -> impl Iterator<Item = &Transaction> {
+        self.rejections
+            .iter()
+            .filter(|rejection| rejection.error == TransactionProcessError::AccountLocked)
+            .map(|rejection| &rejection.transaction)
+    }
+}
 
 pub fn process_transactions(filename: impl AsRef<Path>) -> anyhow::Result<String> {
     let f = File::open(filename).context("Failed to open input file")?;
-    let mut reader = ReaderBuilder::new()
-        .trim(Trim::All)
-        .has_headers(true)
-        .flexible(true)
-        .from_reader(BufReader::new(f));
+    process_reader(standard_reader(BufReader::new(f)))
+}
 
-    let mut processor = TransactionsProcessor::default();
-    for record in reader.deserialize() {
-        let transaction: Transaction = record.context("Failed to deserialize transaction")?;
-        // The errors from transactions are ignored in this function as if transaction has never happened
-        match processor.process(&transaction) {
-            Ok(()) => {
-                info!("Successfully processed transaction {:?}", transaction)
-            }
-            Err(err) => {
-                error!(
-                    "Failed to process transaction {:?}, error: {}",
-                    transaction, err
-                )
-            }
+/// Processes transactions the same way as `process_transactions` (with the same standard reader),
+/// returning typed error for callers matching on it
+pub fn try_process_transactions(filename: impl AsRef<Path>) -> Result<String, ProcessFileError> {
+    let f = File::open(filename)?;
+    let mut reader = standard_reader(BufReader::new(f));
+    let headers = reader.headers()?.clone();
+    let options = ProcessingOptions::default();
+    let mut processor = RecordProcessor::new(headers, &options);
+    for record in reader.into_records() {
+        let record = record?;
+        let line = record.position().map_or(0, Position::line);
+        processor
+            .process_step(record)
+            .map_err(|err| ProcessFileError::Parse {
+                line,
+                source: err.into(),
+            })?;
+    }
+    // Summary is written to memory, so it can fail only on internal errors
+    let report = processor
+        .finish()
+        .map_err(|err| ProcessFileError::Output(err.into()))?;
+    Ok(report.summary)
+}
+
+/// Processes transactions the same way as `process_transactions`, additionally returning the rejected transactions
+pub fn process_transactions_with_rejections(
+    filename: impl AsRef<Path>,
+) -> anyhow::Result<ProcessingReport> {
+    process_transactions_with_options(filename, &ProcessingOptions::default())
+}
+
+/// Processes transactions from the file using non default processing options
+pub fn process_transactions_with_options(
+    filename: impl AsRef<Path>,
+    options: &ProcessingOptions,
+) -> anyhow::Result<ProcessingReport> {
+    let f = File::open(filename).context("Failed to open input file")?;
+    process_transactions_from_reader(BufReader::new(f), options)
+}
+
+/// Processes transactions from the file with client ids mapped according to the given map before processing
+pub fn process_transactions_with_client_map(
+    filename: impl AsRef<Path>,
+    map: &HashMap<ClientId, ClientId>,
+) -> anyhow::Result<ProcessingReport> {
+    process_transactions_with_options(filename, &ProcessingOptions::new().client_map(map.clone()))
+}
+
+/// Processes only the transactions of clients matching the predicate, other rows are skipped as if they were not in the file
+pub fn process_transactions_filtered(
+    filename: impl AsRef<Path>,
+    predicate: impl Fn(ClientId) -> bool,
+) -> anyhow::Result<ProcessingReport> {
+    let f = File::open(filename).context("Failed to open input file")?;
+    let options = ProcessingOptions::default();
+    process_filtered(
+        options_reader(BufReader::new(f), &options),
+        &options,
+        predicate,
+    )
+}
+
+/// Processes transactions from the file, writing the summary of all accounts to `checkpoints`
+/// after every `checkpoint_interval` records, each summary preceded by `# checkpoint N after M records` line
+pub fn process_transactions_with_checkpoints<W: Write>(
+    filename: impl AsRef<Path>,
+    options: &ProcessingOptions,
+    checkpoint_interval: usize,
+    mut checkpoints: W,
+) -> anyhow::Result<ProcessingReport> {
+    anyhow::ensure!(
+        checkpoint_interval > 0,
+        "Checkpoint interval has to be positive"
+    );
+    let f = File::open(filename).context("Failed to open input file")?;
+    let mut driver = ProcessingDriver::new(BufReader::new(f), options)?;
+    let mut records = 0;
+    let mut checkpoint = 0;
+    while driver.step()? {
+        records += 1;
+        if records % checkpoint_interval == 0 {
+            checkpoint += 1;
+            writeln!(
+                checkpoints,
+                "# checkpoint {} after {} records\n{}",
+                checkpoint,
+                records,
+                driver.summary()?.trim_end()
+            )
+            .context("Failed to write checkpoint")?;
+        }
+    }
+    checkpoints.flush().context("Failed to flush checkpoints")?;
+    driver.finish()
+}
+
+/// Processes transactions from the file in the order of their `timestamp` column instead of the order in the file
+/// Every transaction needs a timestamp, transactions with equal timestamps keep the order of the file
+pub fn process_transactions_time_ordered(filename: impl AsRef<Path>) -> anyhow::Result<String> {
+    let f = File::open(filename).context("Failed to open input file")?;
+    let mut reader = standard_reader(BufReader::new(f));
+    let headers = reader.headers().context("Failed to read headers")?.clone();
+    let mut records = vec![];
+    for record in reader.into_records() {
+        let record = record.context("Failed to read record")?;
+        if record.iter().all(str::is_empty) {
+            continue;
         }
+        let transaction: Transaction = record
+            .deserialize(Some(&headers))
+            .context("Failed to deserialize transaction")?;
+        let timestamp = transaction.timestamp.with_context(|| {
+            format!(
+                "Transaction at line {} has no timestamp",
+                record.position().map_or(0, Position::line)
+            )
+        })?;
+        records.push((timestamp, record));
     }
+    // Stable sort keeps the order of the file for equal timestamps
+    records.sort_by_key(|(timestamp, _)| *timestamp);
 
-    into_csv(processor.summary())
+    let options = ProcessingOptions::default();
+    let mut processor = RecordProcessor::new(headers, &options);
+    for (_, record) in records {
+        processor.process_step(record)?;
+    }
+    Ok(processor.finish()?.summary)
 }
 
-fn into_csv(all_summaries: Vec<ClientSummary>) -> anyhow::Result<String> {
-    if all_summaries.is_empty() {
-        // serialize does not add headers if the records are empty
-        Ok("client,available,held,total,locked".to_string())
-    } else {
-        let mut writer = WriterBuilder::new().from_writer(vec![]);
+/// Processes all `*.csv` files of the directory in order of their names as a single input, other files are ignored
+/// Each file has to start with its own header
+pub fn process_transactions_dir(dir: impl AsRef<Path>) -> anyhow::Result<String> {
+    let mut files = fs::read_dir(dir)
+        .context("Failed to read input directory")?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .context("Failed to read input directory entry")?;
+    files.retain(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "csv"));
+    files.sort();
+
+    let options = ProcessingOptions::default();
+    let mut processor = RecordProcessor::new(StringRecord::new(), &options);
+    for path in files {
+        info!("Processing input file {:?}", path);
+        let f = File::open(&path).with_context(|| format!("Failed to open input file {path:?}"))?;
+        let mut reader = standard_reader(BufReader::new(f));
+        processor.set_headers(reader.headers().context("Failed to read headers")?.clone());
+        for record in reader.into_records() {
+            processor.process_step(record.context("Failed to read record")?)?;
+        }
+    }
+    Ok(processor.finish()?.summary)
+}
+
+/// Processes the transactions in memory, returns summaries sorted by client
+/// and the rejected transactions as indexes in the input with the errors
+pub fn run(
+    transactions: Vec<Transaction>,
+) -> (Vec<ClientSummary>, Vec<(usize, TransactionProcessError)>) {
+    let mut processor = TransactionsProcessor::default();
+    let rejections = transactions
+        .iter()
+        .enumerate()
+        .filter_map(|(index, transaction)| {
+            processor.process(transaction).err().map(|err| (index, err))
+        })
+        .collect();
+    (processor.summary(), rejections)
+}
+
+/// Processes transactions from CSV given directly as a string
+pub fn process_transactions_str(csv: &str) -> anyhow::Result<String> {
+    Ok(process_transactions_from_reader(csv.as_bytes(), &ProcessingOptions::default())?.summary)
+}
+
+/// Processes transactions from any source of CSV data, e.g. stdin or in-memory buffer
+pub fn process_transactions_from_reader<R: Read>(
+    input: R,
+    options: &ProcessingOptions,
+) -> anyhow::Result<ProcessingReport> {
+    process_filtered(options_reader(input, options), options, |_| true)
+}
 
-        for summary in all_summaries {
-            writer
-                .serialize(summary)
-                .context("Failed to write summary record")?;
+/// Processes transactions from any source of CSV data, calling `progress` with the number of records
+/// processed so far after every `interval` records, e.g. for a progress indicator
+pub fn process_transactions_from_reader_with_progress<R: Read>(
+    input: R,
+    options: &ProcessingOptions,
+    interval: usize,
+    mut progress: impl FnMut(usize),
+) -> anyhow::Result<ProcessingReport> {
+    anyhow::ensure!(interval > 0, "Progress interval has to be positive");
+    let mut driver = ProcessingDriver::new(input, options)?;
+    let mut records = 0;
+    while driver.step()? {
+        records += 1;
+        if records % interval == 0 {
+            progress(records);
         }
-        let data = String::from_utf8(
-            writer
-                .into_inner()
-                .context("Failed to get buffer from writer")?,
-        )
-        .context("Failed to convert buffer to string")?;
-        Ok(data)
     }
+    driver.finish()
+}
+
+/// Processes transactions from already configured CSV reader (e.g. with custom quoting or comment character)
+/// The reader has to read headers, as the records are deserialized by header names
+pub fn process_reader<R: Read>(mut reader: csv::Reader<R>) -> anyhow::Result<String> {
+    let options = ProcessingOptions::default();
+    let mut processor = RecordProcessor::new(stepping::headers(&mut reader)?, &options);
+    for record in reader.into_records() {
+        processor.process_step(record.context("Failed to read record")?)?;
+    }
+    Ok(processor.finish()?.summary)
+}
+
+/// Returns CSV reader used by default for the input files
+fn standard_reader<R: Read>(input: R) -> csv::Reader<R> {
+    csv_reader(input, true, Trim::All)
+}
+
+/// Returns CSV reader used by default, reading the header row unless the options select headerless input
+/// The input is read with the limit of `ProcessingOptions::max_record_bytes`
+fn options_reader<R: Read>(
+    input: R,
+    options: &ProcessingOptions,
+) -> csv::Reader<RecordBytesLimit<R>> {
+    // Raw rejections keep the whitespace of the input, `RecordProcessor` trims the records after keeping them
+    let trim = if options.keep_raw_rejections {
+        Trim::None
+    } else {
+        Trim::All
+    };
+    csv_reader(
+        RecordBytesLimit::new(input, options.max_record_bytes),
+        !options.headerless,
+        trim,
+    )
+}
+
+fn csv_reader<R: Read>(input: R, has_headers: bool, trim: Trim) -> csv::Reader<R> {
+    // Records are deserialized by header names, so the order of columns in the file does not matter
+    ReaderBuilder::new()
+        .trim(trim)
+        .has_headers(has_headers)
+        .flexible(true)
+        .from_reader(input)
+}
+
+fn process_filtered<R: Read>(
+    reader: csv::Reader<RecordBytesLimit<R>>,
+    options: &ProcessingOptions,
+    client_filter: impl Fn(ClientId) -> bool,
+) -> anyhow::Result<ProcessingReport> {
+    let mut driver = ProcessingDriver::from_reader(reader, options, client_filter)?;
+    while driver.step()? {}
+    driver.finish()
 }
@@ -1,25 +1,82 @@
-use std::path::PathBuf;
+use std::fs::File;
+use std::io::{stdin, BufReader, BufWriter, Read};
+use std::path::{Path, PathBuf};
 use std::process::exit;
 
-use clap::Parser;
+use anyhow::Context;
+use clap::{Parser, ValueEnum};
+use transaction_processor::{OutputFormat, ProcessingOptions};
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 /// Simple processor of transactions
 /// Processes transactions in the input file and returns the account status after processing
 struct Args {
+    /// Path of the input CSV file, `-` reads the input from stdin
     input_filepath: PathBuf,
+    /// Optional path of the CSV file where the rejected transactions are written
+    #[arg(long)]
+    rejections_out: Option<PathBuf>,
+    /// Format of the summary written to stdout
+    #[arg(long, value_enum, default_value_t = Format::Csv)]
+    format: Format,
+    /// Prints the number of processed records to stderr every N records (1000 by default)
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "1000")]
+    progress: Option<usize>,
+    /// Processes only the first N records of the input
+    #[arg(long, value_name = "N")]
+    head: Option<usize>,
+    /// Disables logging, so the log file is not created
+    #[arg(long)]
+    no_log: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Format {
+    Csv,
+    /// Aligned columns for reading in the terminal
+    Table,
+    /// JSON object per client in a separate line
+    Ndjson,
+}
+
+impl From<Format> for OutputFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Csv => OutputFormat::Csv,
+            Format::Table => OutputFormat::Table,
+            Format::Ndjson => OutputFormat::Ndjson,
+        }
+    }
 }
 
 const LOGS_FILENAME: &str = "transaction-processor-logs.log";
 
 fn main() {
     let args = Args::parse();
-    simple_logging::log_to_file(LOGS_FILENAME, log::LevelFilter::Info)
-        .expect("Failed to start logging");
-    match transaction_processor::process_transactions(args.input_filepath) {
-        Ok(transactions_summary) => {
-            println!("{}", transactions_summary);
+    init_logging(!args.no_log).expect("Failed to start logging");
+    let mut options = ProcessingOptions::new().output_format(args.format.into());
+    if let Some(limit) = args.head {
+        options = options.limit(limit);
+    }
+    let result = open_input(&args.input_filepath).and_then(|input| match args.progress {
+        Some(interval) => transaction_processor::process_transactions_from_reader_with_progress(
+            input,
+            &options,
+            interval,
+            |records| eprintln!("Processed {} records", records),
+        ),
+        None => transaction_processor::process_transactions_from_reader(input, &options),
+    });
+    match result {
+        Ok(report) => {
+            println!("{}", report.summary);
+            if let Some(rejections_out) = args.rejections_out {
+                if let Err(err) = write_rejections_file(&rejections_out, &report.rejections) {
+                    eprintln!("Failed to write rejections {:?}", err);
+                    exit(1)
+                }
+            }
         }
         Err(err) => {
             eprintln!("Failed to process input {:?}", err);
@@ -27,3 +84,31 @@ fn main() {
         }
     }
 }
+
+/// Starts logging to `LOGS_FILENAME`, when disabled no logger is installed and all log records are dropped
+fn init_logging(enabled: bool) -> std::io::Result<()> {
+    if enabled {
+        simple_logging::log_to_file(LOGS_FILENAME, log::LevelFilter::Info)
+    } else {
+        log::set_max_level(log::LevelFilter::Off);
+        Ok(())
+    }
+}
+
+/// Opens the input file, `-` stands for stdin
+fn open_input(path: &Path) -> anyhow::Result<Box<dyn Read>> {
+    if path == Path::new("-") {
+        Ok(Box::new(stdin().lock()))
+    } else {
+        let f = File::open(path).context("Failed to open input file")?;
+        Ok(Box::new(BufReader::new(f)))
+    }
+}
+
+fn write_rejections_file(
+    path: &Path,
+    rejections: &[transaction_processor::ProcessingError],
+) -> anyhow::Result<()> {
+    let f = File::create(path).context("Failed to create rejections file")?;
+    transaction_processor::write_rejections(rejections, BufWriter::new(f))
+}
@@ -0,0 +1,166 @@
+use std::io::{BufRead, Write};
+
+use anyhow::Context;
+use csv::{ReaderBuilder, Trim, WriterBuilder};
+use itertools::Itertools;
+
+use crate::amount::{self, Amount, RoundingMode};
+use crate::models::{ClientId, ClientSummary};
+use crate::precision::DECIMAL_PLACES;
+
+/// Balances of the client summed across the shards, as integers with the default precision
+struct MergedSummary {
+    client: ClientId,
+    available: i64,
+    held: i64,
+    total: i64,
+    locked: bool,
+}
+
+impl MergedSummary {
+    fn new(summary: &ClientSummary) -> anyhow::Result<Self> {
+        Ok(Self {
+            client: summary.client,
+            available: to_scaled(summary.available)?,
+            held: to_scaled(summary.held)?,
+            total: to_scaled(summary.total)?,
+            locked: summary.locked,
+        })
+    }
+
+    /// Adds the balances of the same client from another shard, the account is locked if it is locked in any shard
+    fn add(&mut self, summary: &ClientSummary) -> anyhow::Result<()> {
+        let add = |sum: i64, amount: Amount| {
+            sum.checked_add(to_scaled(amount)?)
+                .context("Merged amount out of range")
+        };
+        self.available = add(self.available, summary.available)?;
+        self.held = add(self.held, summary.held)?;
+        self.total = add(self.total, summary.total)?;
+        self.locked |= summary.locked;
+        Ok(())
+    }
+
+    fn summary(&self) -> ClientSummary {
+        ClientSummary {
+            client: self.client,
+            available: amount::from_scaled(self.available, DECIMAL_PLACES),
+            held: amount::from_scaled(self.held, DECIMAL_PLACES),
+            total: amount::from_scaled(self.total, DECIMAL_PLACES),
+            locked: self.locked,
+        }
+    }
+}
+
+/// Amounts are summed as integers to avoid accumulating f64 representation errors
+fn to_scaled(v: Amount) -> anyhow::Result<i64> {
+    amount::to_scaled(v, DECIMAL_PLACES, RoundingMode::HalfUp).context("Amount out of range")
+}
+
+/// Merges CSV summaries of shards sorted by client into a single summary sorted by client,
+/// balances of clients present in multiple shards are summed
+/// The shards are read record by record, so they are never loaded into memory as a whole
+pub fn merge_summary_csvs<R: BufRead, W: Write>(readers: Vec<R>, out: W) -> anyhow::Result<()> {
+    let summaries = readers
+        .into_iter()
+        .map(|reader| {
+            ReaderBuilder::new()
+                .trim(Trim::All)
+                .flexible(true)
+                .from_reader(reader)
+                .into_deserialize::<ClientSummary>()
+        })
+        .kmerge_by(|a, b| match (a, b) {
+            // Errors are returned as soon as they are read
+            (Err(_), _) => true,
+            (_, Err(_)) => false,
+            (Ok(a), Ok(b)) => a.client < b.client,
+        });
+
+    let mut writer = WriterBuilder::new().has_headers(false).from_writer(out);
+    // Header is written explicitly so the summary has it even if there are no clients
+    writer
+        .write_record(["client", "available", "held", "total", "locked"])
+        .context("Failed to write summary header")?;
+    let mut pending: Option<MergedSummary> = None;
+    for summary in summaries {
+        let summary = summary.context("Failed to deserialize client summary")?;
+        match &mut pending {
+            Some(merged) if merged.client == summary.client => merged.add(&summary)?,
+            _ => {
+                if let Some(merged) = pending.replace(MergedSummary::new(&summary)?) {
+                    anyhow::ensure!(
+                        merged.client < summary.client,
+                        "Summary of client {} is not sorted by client",
+                        summary.client
+                    );
+                    writer
+                        .serialize(merged.summary())
+                        .context("Failed to write summary record")?;
+                }
+            }
+        }
+    }
+    if let Some(merged) = pending {
+        writer
+            .serialize(merged.summary())
+            .context("Failed to write summary record")?;
+    }
+    writer.flush().context("Failed to flush summary")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merge(shards: &[&str]) -> anyhow::Result<String> {
+        let mut out = vec![];
+        merge_summary_csvs(
+            shards.iter().map(|shard| shard.as_bytes()).collect(),
+            &mut out,
+        )?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn merge_summary_csvs_should_sum_overlapping_client() {
+        let first = "client,available,held,total,locked\n\
+        1,10.5,0.0,10.5,false\n\
+        3,0.1,0.2,0.3,false\n\
+        4,1.0,0.0,1.0,false\n";
+        let second = "client,available,held,total,locked\n\
+        2,7.0,1.0,8.0,true\n\
+        3,0.2,0.1,0.3,true\n";
+
+        // `Decimal` amounts are written without a redundant fraction
+        let expected = if cfg!(feature = "decimal") {
+            "client,available,held,total,locked\n\
+            1,10.5,0,10.5,false\n\
+            2,7,1,8,true\n\
+            3,0.3,0.3,0.6,true\n\
+            4,1,0,1,false\n"
+        } else {
+            "client,available,held,total,locked\n\
+            1,10.5,0.0,10.5,false\n\
+            2,7.0,1.0,8.0,true\n\
+            3,0.3,0.3,0.6,true\n\
+            4,1.0,0.0,1.0,false\n"
+        };
+        assert_eq!(merge(&[first, second]).unwrap(), expected);
+    }
+
+    #[test]
+    fn merge_summary_csvs_should_fail_for_unsorted_shard() {
+        let shard = "client,available,held,total,locked\n\
+        2,1.0,0.0,1.0,false\n\
+        1,1.0,0.0,1.0,false\n";
+
+        assert!(merge(&[shard]).is_err());
+    }
+
+    #[test]
+    fn merge_summary_csvs_without_shards_should_write_only_header() {
+        assert_eq!(merge(&[]).unwrap(), "client,available,held,total,locked\n");
+    }
+}
@@ -0,0 +1,37 @@
+use std::collections::HashMap;
+use std::ops::AddAssign;
+
+use crate::models::ClientId;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+/// Counts of successfully processed dispute lifecycle transactions
+pub struct DisputeCounts {
+    pub opened: u64,
+    pub resolved: u64,
+    pub charged_back: u64,
+}
+
+impl AddAssign for DisputeCounts {
+    fn add_assign(&mut self, other: Self) {
+        self.opened += other.opened;
+        self.resolved += other.resolved;
+        self.charged_back += other.charged_back;
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+/// Counts of deposits, withdrawals and opening balances of a client in each `TransactionStatus`
+pub struct StatusCounts {
+    pub processed: usize,
+    pub under_dispute: usize,
+    pub charged_back: usize,
+    pub settled: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// Dispute counts of all clients, failed dispute transactions are not counted
+pub struct DisputeMetrics {
+    pub total: DisputeCounts,
+    /// Counts of clients with at least one dispute
+    pub per_client: HashMap<ClientId, DisputeCounts>,
+}
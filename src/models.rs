@@ -1,34 +1,478 @@
-use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 
-#[derive(Debug, Copy, Clone, Deserialize)]
+use num_traits::Zero;
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::amount::{self, Amount, RoundingMode};
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
 #[serde(rename_all = "lowercase")]
-pub(crate) enum TransactionType {
+pub enum TransactionType {
     Deposit,
     Withdrawal,
     Dispute,
     Resolve,
     Chargeback,
+    /// Administrative lock of the account, independent from chargebacks
+    Freeze,
+    /// Removes the administrative lock set by `Freeze`
+    Unfreeze,
+    /// Sets the available founds of a client without any prior transactions
+    #[serde(rename = "opening")]
+    OpeningBalance,
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+/// Errors of parsing the type of the transaction
+pub enum TransactionTypeParseError {
+    #[error("Empty transaction type")]
+    Empty,
+
+    #[error("Unknown transaction type {0:?}")]
+    Unknown(String),
+}
+
+impl TransactionType {
+    /// All the transaction types
+    pub const ALL: [TransactionType; 8] = [
+        TransactionType::Deposit,
+        TransactionType::Withdrawal,
+        TransactionType::Dispute,
+        TransactionType::Resolve,
+        TransactionType::Chargeback,
+        TransactionType::Freeze,
+        TransactionType::Unfreeze,
+        TransactionType::OpeningBalance,
+    ];
+
+    /// Canonical lowercase name of the type, the same as in the input CSV
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionType::Deposit => "deposit",
+            TransactionType::Withdrawal => "withdrawal",
+            TransactionType::Dispute => "dispute",
+            TransactionType::Resolve => "resolve",
+            TransactionType::Chargeback => "chargeback",
+            TransactionType::Freeze => "freeze",
+            TransactionType::Unfreeze => "unfreeze",
+            TransactionType::OpeningBalance => "opening",
+        }
+    }
+}
+
+impl Display for TransactionType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TransactionType {
+    type Err = TransactionTypeParseError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Err(TransactionTypeParseError::Empty);
+        }
+        TransactionType::ALL
+            .into_iter()
+            .find(|transaction_type| transaction_type.as_str() == value)
+            .ok_or_else(|| TransactionTypeParseError::Unknown(value.to_string()))
+    }
+}
+
+impl<'de> Deserialize<'de> for TransactionType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
 }
 
-pub(crate) type ClientId = u64;
-pub(crate) type TransactionId = u64;
+pub type ClientId = u64;
+pub type TransactionId = u64;
 
-#[derive(Debug, Clone, Deserialize)]
-pub(crate) struct Transaction {
+/// Deserializes client or transaction id given as a number or as text, e.g. quoted `"007"` or `+1`,
+/// with an error naming the invalid value for non-numeric text
+fn deserialize_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    struct IdVisitor;
+
+    impl Visitor<'_> for IdVisitor {
+        type Value = u64;
+
+        fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+            f.write_str("a non-negative integer id")
+        }
+
+        fn visit_u64<E: de::Error>(self, v: u64) -> Result<u64, E> {
+            Ok(v)
+        }
+
+        fn visit_i64<E: de::Error>(self, v: i64) -> Result<u64, E> {
+            u64::try_from(v)
+                .map_err(|_| E::custom(format!("invalid id {v}, expected a non-negative integer")))
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<u64, E> {
+            // Leading `+` and zeros are accepted by `u64::from_str`
+            v.trim().parse().map_err(|_| {
+                E::custom(format!("invalid id {v:?}, expected a non-negative integer"))
+            })
+        }
+    }
+
+    deserializer.deserialize_any(IdVisitor)
+}
+/// Time of the transaction, e.g. unix epoch seconds, only the order of the values matters
+pub type Timestamp = u64;
+
+/// Columns of the input CSV matching the serde names of `Transaction` fields, the order in the file does not matter
+/// The optional `timestamp` column is not part of the required schema
+pub const INPUT_HEADERS: [&str; 4] = ["type", "client", "tx", "amount"];
+/// Column of the transaction type in `INPUT_HEADERS`
+pub(crate) const TYPE_HEADER: &str = INPUT_HEADERS[0];
+/// Column of the amount in `INPUT_HEADERS`
+pub(crate) const AMOUNT_HEADER: &str = INPUT_HEADERS[3];
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Transaction {
     #[serde(rename = "type")]
-    pub(crate) transaction_type: TransactionType,
-    pub(crate) client: ClientId,
-    #[serde(rename = "tx")]
-    pub(crate) transaction_id: TransactionId,
-    pub(crate) amount: Option<f64>,
+    pub transaction_type: TransactionType,
+    #[serde(deserialize_with = "deserialize_id")]
+    pub client: ClientId,
+    #[serde(rename = "tx", deserialize_with = "deserialize_id")]
+    pub transaction_id: TransactionId,
+    /// Empty cell (e.g. trailing comma in `dispute,1,1,`) is read as no amount, same as missing column
+    pub amount: Option<Amount>,
+    /// Optional `timestamp` column, used by `process_transactions_time_ordered`
+    pub timestamp: Option<Timestamp>,
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+/// Structural errors of a transaction, independent from any client state
+pub enum TransactionValidationError {
+    #[error("Missing required amount value")]
+    MissingAmountValue,
+
+    #[error("Non positive amount in transaction")]
+    NonPositiveAmount,
+
+    #[error("Non finite amount in transaction")]
+    NonFiniteAmount,
+}
+
+impl Transaction {
+    /// Checks if the transaction is structurally valid, without checking it against any client state
+    /// Deposits, withdrawals and opening balances need a positive finite amount, other transaction types do not require amount
+    pub fn validate(&self) -> Result<(), TransactionValidationError> {
+        match self.transaction_type {
+            TransactionType::Deposit
+            | TransactionType::Withdrawal
+            | TransactionType::OpeningBalance => {
+                let amount = self
+                    .amount
+                    .ok_or(TransactionValidationError::MissingAmountValue)?;
+                if !amount::is_finite(amount) {
+                    Err(TransactionValidationError::NonFiniteAmount)
+                } else if amount <= Amount::zero() {
+                    Err(TransactionValidationError::NonPositiveAmount)
+                } else {
+                    Ok(())
+                }
+            }
+            TransactionType::Dispute
+            | TransactionType::Resolve
+            | TransactionType::Chargeback
+            | TransactionType::Freeze
+            | TransactionType::Unfreeze => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ClientSummary {
+    pub client: ClientId,
+    pub available: Amount,
+    pub held: Amount,
+    pub total: Amount,
+    pub locked: bool,
 }
 
 #[derive(Debug, Clone, Serialize, PartialEq)]
-pub(crate) struct ClientSummary {
-    pub(crate) client: ClientId,
-    pub(crate) available: f64,
-    pub(crate) held: f64,
-    pub(crate) total: f64,
-    pub(crate) locked: bool,
+/// Balances summed across all client accounts
+pub struct GrandTotals {
+    pub available: Amount,
+    pub held: Amount,
+    pub total: Amount,
+    /// Number of locked accounts
+    pub locked_count: usize,
+}
+
+impl ClientSummary {
+    /// Creates the summary with total calculated as available plus held
+    pub fn new(client: ClientId, available: Amount, held: Amount, locked: bool) -> Self {
+        Self {
+            client,
+            available,
+            held,
+            total: available + held,
+            locked,
+        }
+    }
+
+    /// Compares the summaries with amounts rounded to the given number of decimal places,
+    /// so the representation noise of f64 arithmetic is ignored
+    pub fn approx_eq(&self, other: &ClientSummary, places: u32) -> bool {
+        let amount_eq = |a: Amount, b: Amount| {
+            amount::to_scaled::<i128>(a, places, RoundingMode::HalfUp)
+                == amount::to_scaled::<i128>(b, places, RoundingMode::HalfUp)
+        };
+        self.client == other.client
+            && self.locked == other.locked
+            && amount_eq(self.available, other.available)
+            && amount_eq(self.held, other.held)
+            && amount_eq(self.total, other.total)
+    }
 }
 // TODO: Formatting for f64
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amount::amount;
+
+    fn transaction(transaction_type: TransactionType, amount: Option<Amount>) -> Transaction {
+        Transaction {
+            transaction_type,
+            client: 1,
+            transaction_id: 1,
+            amount,
+            timestamp: None,
+        }
+    }
+
+    #[test]
+    fn client_summary_new_should_calculate_total() {
+        assert_eq!(
+            ClientSummary::new(1, amount("100.0"), amount("20.5"), false),
+            ClientSummary {
+                client: 1,
+                available: amount("100.0"),
+                held: amount("20.5"),
+                total: amount("120.5"),
+                locked: false,
+            }
+        );
+        assert_eq!(
+            ClientSummary::new(3, amount("-300.0"), amount("0.0"), true).total,
+            amount("-300.0")
+        );
+        assert_eq!(
+            ClientSummary::new(4, amount("-50.0"), amount("20.0"), false).total,
+            amount("-30.0")
+        );
+    }
+
+    #[test]
+    fn transaction_should_round_trip_through_csv_and_json() {
+        let deposit = transaction(TransactionType::Deposit, Some(amount("12.5")));
+
+        let mut writer = csv::Writer::from_writer(vec![]);
+        writer.serialize(&deposit).unwrap();
+        let csv = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+        assert_eq!(csv, "type,client,tx,amount,timestamp\ndeposit,1,1,12.5,\n");
+        let parsed = csv::Reader::from_reader(csv.as_bytes())
+            .deserialize::<Transaction>()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(parsed, deposit);
+
+        let json = serde_json::to_string(&deposit).unwrap();
+        // `Decimal` amounts are serialized to JSON as strings
+        let expected = if cfg!(feature = "decimal") {
+            "{\"type\":\"deposit\",\"client\":1,\"tx\":1,\"amount\":\"12.5\",\"timestamp\":null}"
+        } else {
+            "{\"type\":\"deposit\",\"client\":1,\"tx\":1,\"amount\":12.5,\"timestamp\":null}"
+        };
+        assert_eq!(json, expected);
+        assert_eq!(serde_json::from_str::<Transaction>(&json).unwrap(), deposit);
+    }
+
+    fn parse_ids(client: &str, tx: &str) -> Result<(ClientId, TransactionId), csv::Error> {
+        let input = format!("type,client,tx,amount\n\"deposit\",{client},{tx},\"1.0\"\n");
+        let mut reader = csv::Reader::from_reader(input.as_bytes());
+        let transaction: Transaction = reader.deserialize().next().unwrap()?;
+        Ok((transaction.client, transaction.transaction_id))
+    }
+
+    #[test]
+    fn quoted_ids_with_leading_zeros_and_plus_should_be_parsed() {
+        assert_eq!(parse_ids("\"007\"", "\"+1\"").unwrap(), (7, 1));
+        assert_eq!(parse_ids("+1", "007").unwrap(), (1, 7));
+    }
+
+    #[test]
+    fn non_numeric_id_should_fail_with_invalid_value() {
+        let err = parse_ids("\"abc\"", "1").unwrap_err();
+        assert!(err.to_string().contains("invalid id \"abc\""), "{err}");
+        assert!(parse_ids("1", "-1").is_err());
+        assert!(parse_ids("1", "1.5").is_err());
+    }
+
+    #[test]
+    fn input_headers_should_match_transaction_fields() {
+        let headers = csv::StringRecord::from(INPUT_HEADERS.to_vec());
+        let record = csv::StringRecord::from(vec!["deposit", "1", "2", "3.5"]);
+        let transaction: Transaction = record.deserialize(Some(&headers)).unwrap();
+        assert_eq!(
+            transaction,
+            Transaction {
+                transaction_type: TransactionType::Deposit,
+                client: 1,
+                transaction_id: 2,
+                amount: Some(amount("3.5")),
+                timestamp: None,
+            }
+        );
+
+        // Every required column is known to serde, only amount may be missing
+        for (index, header) in INPUT_HEADERS.into_iter().enumerate() {
+            let mut headers = INPUT_HEADERS.to_vec();
+            headers[index] = "unknown";
+            let result = record.deserialize::<Transaction>(Some(&csv::StringRecord::from(headers)));
+            if header == AMOUNT_HEADER {
+                assert_eq!(result.unwrap().amount, None);
+            } else {
+                let err = result.unwrap_err().to_string();
+                assert!(err.contains(&format!("missing field `{header}`")), "{err}");
+            }
+        }
+    }
+
+    #[test]
+    fn empty_amount_cell_should_be_none_for_every_transaction_type() {
+        for transaction_type in TransactionType::ALL {
+            let input = format!("type,client,tx,amount\n{transaction_type},1,1,\n");
+            let mut reader = csv::Reader::from_reader(input.as_bytes());
+            let transaction: Transaction = reader.deserialize().next().unwrap().unwrap();
+            assert_eq!(transaction.amount, None, "{transaction_type}");
+        }
+    }
+
+    #[test]
+    fn transaction_type_should_round_trip_through_string() {
+        for transaction_type in TransactionType::ALL {
+            assert_eq!(
+                transaction_type.to_string().parse(),
+                Ok(transaction_type),
+                "{transaction_type:?}"
+            );
+        }
+        assert_eq!(TransactionType::Chargeback.to_string(), "chargeback");
+        assert_eq!(
+            "Deposit".parse::<TransactionType>(),
+            Err(TransactionTypeParseError::Unknown("Deposit".to_string()))
+        );
+    }
+
+    #[test]
+    fn parse_transaction_type_should_distinguish_empty_and_unknown() {
+        assert_eq!("deposit".parse(), Ok(TransactionType::Deposit));
+        assert_eq!("unfreeze".parse(), Ok(TransactionType::Unfreeze));
+        assert_eq!("opening".parse(), Ok(TransactionType::OpeningBalance));
+        assert_eq!(
+            " ".parse::<TransactionType>(),
+            Err(TransactionTypeParseError::Empty)
+        );
+        assert_eq!(
+            "depsit".parse::<TransactionType>(),
+            Err(TransactionTypeParseError::Unknown("depsit".to_string()))
+        );
+    }
+
+    #[test]
+    fn deposit_and_withdrawal_with_positive_amount_should_be_valid() {
+        assert_eq!(
+            transaction(TransactionType::Deposit, Some(amount("10.0"))).validate(),
+            Ok(())
+        );
+        assert_eq!(
+            transaction(TransactionType::Withdrawal, Some(amount("0.0001"))).validate(),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn deposit_and_withdrawal_without_amount_should_be_invalid() {
+        assert_eq!(
+            transaction(TransactionType::Deposit, None).validate(),
+            Err(TransactionValidationError::MissingAmountValue)
+        );
+        assert_eq!(
+            transaction(TransactionType::Withdrawal, None).validate(),
+            Err(TransactionValidationError::MissingAmountValue)
+        );
+    }
+
+    #[test]
+    fn deposit_and_withdrawal_with_non_positive_amount_should_be_invalid() {
+        assert_eq!(
+            transaction(TransactionType::Deposit, Some(amount("0.0"))).validate(),
+            Err(TransactionValidationError::NonPositiveAmount)
+        );
+        assert_eq!(
+            transaction(TransactionType::Withdrawal, Some(amount("-1.0"))).validate(),
+            Err(TransactionValidationError::NonPositiveAmount)
+        );
+    }
+
+    // `Decimal` amounts are always finite
+    #[cfg(not(feature = "decimal"))]
+    #[test]
+    fn deposit_and_withdrawal_with_non_finite_amount_should_be_invalid() {
+        assert_eq!(
+            transaction(TransactionType::Deposit, Some(f64::INFINITY)).validate(),
+            Err(TransactionValidationError::NonFiniteAmount)
+        );
+        assert_eq!(
+            transaction(TransactionType::Withdrawal, Some(f64::NAN)).validate(),
+            Err(TransactionValidationError::NonFiniteAmount)
+        );
+    }
+
+    #[test]
+    fn dispute_resolve_and_chargeback_should_not_require_amount() {
+        assert_eq!(
+            transaction(TransactionType::Dispute, None).validate(),
+            Ok(())
+        );
+        assert_eq!(
+            transaction(TransactionType::Resolve, None).validate(),
+            Ok(())
+        );
+        assert_eq!(
+            transaction(TransactionType::Chargeback, None).validate(),
+            Ok(())
+        );
+    }
+
+    #[cfg(not(feature = "decimal"))]
+    #[test]
+    fn approx_eq_should_ignore_f64_representation_noise() {
+        let summary = |available: Amount| ClientSummary {
+            client: 1,
+            available,
+            held: amount("0.0"),
+            total: available,
+            locked: false,
+        };
+        let sum = summary(amount("0.1") + amount("0.2"));
+        let expected = summary(amount("0.3"));
+
+        assert_ne!(sum, expected);
+        assert!(sum.approx_eq(&expected, 4));
+        assert!(!sum.approx_eq(&summary(amount("0.3001")), 4));
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+use crate::input::{DecimalSeparator, UnknownTypePolicy};
+use crate::models::ClientId;
+use crate::output::{AmountFormat, LineTerminator, OutputFormat, QuoteStyle};
+
+#[derive(Debug, Clone, Default)]
+/// Options of reading and processing the input CSV and writing the summary,
+/// the defaults match `process_transactions` behavior
+pub struct ProcessingOptions {
+    pub(crate) skip_repeated_headers: bool,
+    pub(crate) include_net: bool,
+    pub(crate) max_rejections: Option<usize>,
+    pub(crate) decimal_separator: DecimalSeparator,
+    pub(crate) client_map: HashMap<ClientId, ClientId>,
+    pub(crate) amount_format: AmountFormat,
+    pub(crate) fixed_decimal_places: Option<usize>,
+    pub(crate) max_record_bytes: Option<usize>,
+    pub(crate) currency: Option<String>,
+    pub(crate) client_currencies: HashMap<ClientId, String>,
+    pub(crate) halt_on_lock: bool,
+    pub(crate) quote_style: QuoteStyle,
+    pub(crate) line_terminator: LineTerminator,
+    pub(crate) active_only: bool,
+    pub(crate) output_format: OutputFormat,
+    pub(crate) on_unknown_type: UnknownTypePolicy,
+    pub(crate) headerless: bool,
+    pub(crate) keep_raw_rejections: bool,
+    pub(crate) limit: Option<usize>,
+    pub(crate) drop_zero_held: bool,
+}
+
+impl ProcessingOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Skips the data rows that are exactly the same as the header (e.g. when multiple files were concatenated),
+    /// otherwise such rows fail to deserialize and abort processing
+    pub fn skip_repeated_headers(mut self, skip: bool) -> Self {
+        self.skip_repeated_headers = skip;
+        self
+    }
+
+    /// Adds `net` column (available minus held) at the end of the summary
+    pub fn include_net(mut self, include: bool) -> Self {
+        self.include_net = include;
+        self
+    }
+
+    /// Stops processing as soon as the given number of transactions is rejected,
+    /// the summary contains only the transactions processed until then
+    pub fn max_rejections(mut self, max_rejections: usize) -> Self {
+        self.max_rejections = Some(max_rejections);
+        self
+    }
+
+    /// Decimal separator of amounts in the input (`DecimalSeparator::Dot` by default)
+    pub fn decimal_separator(mut self, decimal_separator: DecimalSeparator) -> Self {
+        self.decimal_separator = decimal_separator;
+        self
+    }
+
+    /// Maps client ids of the input before processing, unmapped ids are not changed
+    /// Clients mapped to the same id share one account
+    pub fn client_map(mut self, client_map: HashMap<ClientId, ClientId>) -> Self {
+        self.client_map = client_map;
+        self
+    }
+
+    /// Format of the amounts in the summary (`AmountFormat::Decimal` by default)
+    pub fn amount_format(mut self, amount_format: AmountFormat) -> Self {
+        self.amount_format = amount_format;
+        self
+    }
+
+    /// Writes decimal amounts with exactly the given number of fractional digits, e.g. `130.00` for 2 places
+    /// By default amounts are written with as few digits as needed (e.g. `130.0`)
+    pub fn fixed_decimal_places(mut self, places: usize) -> Self {
+        self.fixed_decimal_places = Some(places);
+        self
+    }
+
+    /// Aborts processing on the first record with more bytes than the limit (e.g. maliciously long field)
+    /// The limit is checked while reading the input, so the record is never buffered beyond it
+    pub fn max_record_bytes(mut self, max_record_bytes: usize) -> Self {
+        self.max_record_bytes = Some(max_record_bytes);
+        self
+    }
+
+    /// Adds `currency` column after the amounts in the summary with the given currency code for all clients
+    /// In `OutputFormat::Ndjson` the currency is written as `currency` field of each object
+    pub fn currency(mut self, currency: impl Into<String>) -> Self {
+        self.currency = Some(currency.into());
+        self
+    }
+
+    /// Currency codes of specific clients in the `currency` column, overriding the currency of the whole run
+    /// Clients without a currency have an empty value in the column
+    pub fn client_currencies(mut self, client_currencies: HashMap<ClientId, String>) -> Self {
+        self.client_currencies = client_currencies;
+        self
+    }
+
+    /// Stops processing right after the first transaction locking an account (chargeback),
+    /// the client that triggered the halt is returned in the report
+    pub fn halt_on_lock(mut self, halt: bool) -> Self {
+        self.halt_on_lock = halt;
+        self
+    }
+
+    /// Quoting of the fields in the summary (`QuoteStyle::Necessary` by default)
+    pub fn quote_style(mut self, quote_style: QuoteStyle) -> Self {
+        self.quote_style = quote_style;
+        self
+    }
+
+    /// Line terminator of the summary rows (`LineTerminator::Lf` by default)
+    pub fn line_terminator(mut self, line_terminator: LineTerminator) -> Self {
+        self.line_terminator = line_terminator;
+        self
+    }
+
+    /// Skips in the summary the clients whose transactions were all rejected, see `TransactionsProcessorBuilder::active_only`
+    pub fn active_only(mut self, active_only: bool) -> Self {
+        self.active_only = active_only;
+        self
+    }
+
+    /// Format of the summary (`OutputFormat::Csv` by default)
+    pub fn output_format(mut self, output_format: OutputFormat) -> Self {
+        self.output_format = output_format;
+        self
+    }
+
+    /// Handling of rows with unknown transaction type (`UnknownTypePolicy::Abort` by default)
+    pub fn on_unknown_type(mut self, policy: UnknownTypePolicy) -> Self {
+        self.on_unknown_type = policy;
+        self
+    }
+
+    /// Reads input without a header row, the columns are interpreted positionally as `INPUT_HEADERS`
+    pub fn headerless(mut self, headerless: bool) -> Self {
+        self.headerless = headerless;
+        self
+    }
+
+    /// Keeps the input record of each rejected transaction in `ProcessingError::raw`, see `write_raw_rejections`
+    pub fn keep_raw_rejections(mut self, keep: bool) -> Self {
+        self.keep_raw_rejections = keep;
+        self
+    }
+
+    /// Processes only the first `limit` records of the input, e.g. for sampling of huge files
+    /// All records read count towards the limit, also the blank and skipped ones
+    /// The records after the limit are not parsed, the processing is reported as truncated if there is any
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Omits the `held` column of the summary when the held founds of all clients are zero (e.g. no disputes)
+    pub fn drop_zero_held(mut self, drop: bool) -> Self {
+        self.drop_zero_held = drop;
+        self
+    }
+
+    /// Returns true if the summary should have the `currency` column
+    pub(crate) fn has_currency(&self) -> bool {
+        self.currency.is_some() || !self.client_currencies.is_empty()
+    }
+
+    /// Returns the currency code of the client, `None` when no currency is configured for the client
+    pub(crate) fn client_currency(&self, client: ClientId) -> Option<&str> {
+        self.client_currencies
+            .get(&client)
+            .or(self.currency.as_ref())
+            .map(String::as_str)
+    }
+}
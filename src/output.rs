@@ -0,0 +1,405 @@
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+
+use anyhow::Context;
+use csv::{Terminator, WriterBuilder};
+use itertools::Itertools;
+use num_traits::Zero;
+use serde::{Serialize, Serializer};
+
+use crate::amount::Amount;
+use crate::models::{ClientId, ClientSummary};
+use crate::options::ProcessingOptions;
+use crate::processor::TransactionsProcessor;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+/// Format of the amounts in the summary output
+pub enum AmountFormat {
+    /// Decimal amounts, e.g. `130.123`
+    #[default]
+    Decimal,
+    /// Internal integer amounts scaled by `10^decimal_places`, e.g. `1301230` for `130.123` at 4 decimal places
+    MinorUnits,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+/// Quoting of the fields in the summary output
+pub enum QuoteStyle {
+    /// Quotes only the fields containing special characters
+    #[default]
+    Necessary,
+    /// Quotes all the fields
+    Always,
+}
+
+impl From<QuoteStyle> for csv::QuoteStyle {
+    fn from(style: QuoteStyle) -> Self {
+        match style {
+            QuoteStyle::Necessary => csv::QuoteStyle::Necessary,
+            QuoteStyle::Always => csv::QuoteStyle::Always,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+/// Line terminator of the summary output
+pub enum LineTerminator {
+    /// `\n`
+    #[default]
+    Lf,
+    /// `\r\n`
+    CrLf,
+}
+
+impl From<LineTerminator> for Terminator {
+    fn from(terminator: LineTerminator) -> Self {
+        match terminator {
+            LineTerminator::Lf => Terminator::Any(b'\n'),
+            LineTerminator::CrLf => Terminator::CRLF,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+/// Format of the whole summary output
+pub enum OutputFormat {
+    /// CSV with a header
+    #[default]
+    Csv,
+    /// Right-aligned table with columns as wide as their widest value, for reading in the terminal
+    Table,
+    /// Compact JSON object per client in a separate line, with the same fields as the CSV columns
+    Ndjson,
+}
+
+/// Number of fractional digits of decimal amounts in `OutputFormat::Table` unless fixed decimal places are set
+const TABLE_DECIMAL_PLACES: usize = 4;
+
+/// Amount column value in the selected `AmountFormat`
+enum AmountValue {
+    Decimal(Amount),
+    /// Decimal with exactly the given number of fractional digits
+    FixedDecimal(Amount, usize),
+    MinorUnits(i64),
+}
+
+impl AmountValue {
+    fn decimal(value: Amount, options: &ProcessingOptions) -> Self {
+        let places = match options.output_format {
+            OutputFormat::Csv | OutputFormat::Ndjson => options.fixed_decimal_places,
+            OutputFormat::Table => {
+                Some(options.fixed_decimal_places.unwrap_or(TABLE_DECIMAL_PLACES))
+            }
+        };
+        match places {
+            Some(places) => AmountValue::FixedDecimal(value, places),
+            None => AmountValue::Decimal(value),
+        }
+    }
+}
+
+impl Display for AmountValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AmountValue::Decimal(value) => write!(f, "{value}"),
+            AmountValue::FixedDecimal(value, places) => write!(f, "{value:.places$}"),
+            AmountValue::MinorUnits(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+impl Serialize for AmountValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            AmountValue::Decimal(value) => Serialize::serialize(value, serializer),
+            AmountValue::FixedDecimal(value, places) => {
+                serializer.collect_str(&format_args!("{value:.places$}"))
+            }
+            AmountValue::MinorUnits(value) => serializer.serialize_i64(*value),
+        }
+    }
+}
+
+#[derive(Serialize)]
+/// Single row of the summary output, optional columns are skipped when not enabled
+struct SummaryRecord {
+    client: ClientId,
+    available: AmountValue,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    held: Option<AmountValue>,
+    total: AmountValue,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    currency: Option<String>,
+    locked: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    net: Option<AmountValue>,
+}
+
+impl SummaryRecord {
+    fn new(
+        summary: &ClientSummary,
+        processor: &TransactionsProcessor,
+        options: &ProcessingOptions,
+    ) -> Self {
+        match options.amount_format {
+            AmountFormat::Decimal => Self {
+                client: summary.client,
+                available: AmountValue::decimal(summary.available, options),
+                held: Some(AmountValue::decimal(summary.held, options)),
+                total: AmountValue::decimal(summary.total, options),
+                currency: SummaryRecord::currency(summary.client, options),
+                locked: summary.locked,
+                net: options
+                    .include_net
+                    .then(|| AmountValue::decimal(processor.net(summary.client), options)),
+            },
+            AmountFormat::MinorUnits => {
+                let (available, held, total) = processor.minor_units(summary.client);
+                Self {
+                    client: summary.client,
+                    available: AmountValue::MinorUnits(available),
+                    held: Some(AmountValue::MinorUnits(held)),
+                    total: AmountValue::MinorUnits(total),
+                    currency: SummaryRecord::currency(summary.client, options),
+                    locked: summary.locked,
+                    net: options
+                        .include_net
+                        .then(|| AmountValue::MinorUnits(available - held)),
+                }
+            }
+        }
+    }
+
+    /// Returns values of the columns as text, in the order of `summary_columns`
+    fn cells(&self) -> Vec<String> {
+        let mut cells = vec![self.client.to_string(), self.available.to_string()];
+        cells.extend(self.held.as_ref().map(AmountValue::to_string));
+        cells.push(self.total.to_string());
+        cells.extend(self.currency.clone());
+        cells.push(self.locked.to_string());
+        cells.extend(self.net.as_ref().map(AmountValue::to_string));
+        cells
+    }
+
+    /// Currency column value, empty for clients without currency when the column is enabled
+    fn currency(client: ClientId, options: &ProcessingOptions) -> Option<String> {
+        options.has_currency().then(|| {
+            options
+                .client_currency(client)
+                .unwrap_or_default()
+                .to_string()
+        })
+    }
+}
+
+/// Returns the columns of the summary, matching the fields of `SummaryRecord`
+fn summary_columns(options: &ProcessingOptions, include_held: bool) -> Vec<&'static str> {
+    let mut columns = vec!["client", "available"];
+    if include_held {
+        columns.push("held");
+    }
+    columns.push("total");
+    if options.has_currency() {
+        columns.push("currency");
+    }
+    columns.push("locked");
+    if options.include_net {
+        columns.push("net");
+    }
+    columns
+}
+
+/// Returns the header of the CSV summary
+fn summary_header(options: &ProcessingOptions, include_held: bool) -> String {
+    let columns = summary_columns(options, include_held);
+    match options.quote_style {
+        QuoteStyle::Necessary => columns.join(","),
+        QuoteStyle::Always => columns
+            .iter()
+            .map(|column| format!("\"{column}\""))
+            .join(","),
+    }
+}
+
+/// Returns false if the `held` column should be dropped, i.e. it is enabled by the options and all held founds are zero
+fn includes_held(processor: &TransactionsProcessor, options: &ProcessingOptions) -> bool {
+    // Held founds are never negative, so their sum is zero only if all of them are zero
+    !options.drop_zero_held || processor.grand_totals().held != Amount::zero()
+}
+
+/// Returns summary records of all client accounts of the processor
+fn summary_records(
+    processor: &TransactionsProcessor,
+    options: &ProcessingOptions,
+    include_held: bool,
+) -> Vec<SummaryRecord> {
+    processor
+        .summary()
+        .iter()
+        .map(|summary| {
+            let mut record = SummaryRecord::new(summary, processor, options);
+            if !include_held {
+                record.held = None;
+            }
+            record
+        })
+        .collect()
+}
+
+/// Writes the summary of all client accounts of the processor in the selected `OutputFormat`
+pub(crate) fn render_summary(
+    processor: &TransactionsProcessor,
+    options: &ProcessingOptions,
+) -> anyhow::Result<String> {
+    match options.output_format {
+        OutputFormat::Csv => into_csv(processor, options),
+        OutputFormat::Table => Ok(into_table(processor, options)),
+        OutputFormat::Ndjson => into_ndjson(processor, options),
+    }
+}
+
+/// Writes the summary of all client accounts of the processor as a right-aligned table
+fn into_table(processor: &TransactionsProcessor, options: &ProcessingOptions) -> String {
+    let include_held = includes_held(processor, options);
+    let header = summary_columns(options, include_held)
+        .into_iter()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    let rows = summary_records(processor, options, include_held)
+        .iter()
+        .map(SummaryRecord::cells)
+        .collect::<Vec<_>>();
+    let widths = header
+        .iter()
+        .enumerate()
+        .map(|(index, column)| {
+            rows.iter()
+                .map(|row| row[index].len())
+                .chain([column.len()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+
+    let format_row = |row: &[String]| {
+        row.iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:>width$}"))
+            .join("  ")
+    };
+    let mut table = format_row(&header) + "\n";
+    table += &widths.iter().map(|width| "-".repeat(*width)).join("  ");
+    table += "\n";
+    for row in &rows {
+        table += &format_row(row);
+        table += "\n";
+    }
+    table
+}
+
+/// Writes the summary of all client accounts of the processor as CSV
+fn into_csv(
+    processor: &TransactionsProcessor,
+    options: &ProcessingOptions,
+) -> anyhow::Result<String> {
+    let include_held = includes_held(processor, options);
+    let records = summary_records(processor, options, include_held);
+    if records.is_empty() {
+        // serialize does not add headers if the records are empty
+        Ok(summary_header(options, include_held))
+    } else {
+        let mut writer = WriterBuilder::new()
+            .quote_style(options.quote_style.into())
+            .terminator(options.line_terminator.into())
+            .from_writer(vec![]);
+
+        for record in records {
+            writer
+                .serialize(record)
+                .context("Failed to write summary record")?;
+        }
+        let data = String::from_utf8(
+            writer
+                .into_inner()
+                .context("Failed to get buffer from writer")?,
+        )
+        .context("Failed to convert buffer to string")?;
+        Ok(data)
+    }
+}
+
+/// Writes the summary of all client accounts of the processor as NDJSON, nothing is written for no clients
+fn into_ndjson(
+    processor: &TransactionsProcessor,
+    options: &ProcessingOptions,
+) -> anyhow::Result<String> {
+    let include_held = includes_held(processor, options);
+    let mut data = String::new();
+    for record in summary_records(processor, options, include_held) {
+        data += &serde_json::to_string(&record).context("Failed to write summary as JSON")?;
+        data += "\n";
+    }
+    Ok(data)
+}
+
+/// Writes each summary as a compact JSON object in a separate line (NDJSON), nothing is written for no summaries
+/// Amounts are written as numbers with the processor precision (4 decimal places by default)
+/// Optional columns of `ProcessingOptions` (e.g. currency) are not written, see `OutputFormat::Ndjson` for them
+pub fn write_summary_ndjson<W: Write>(
+    summaries: &[ClientSummary],
+    mut out: W,
+) -> anyhow::Result<()> {
+    for summary in summaries {
+        serde_json::to_writer(&mut out, summary).context("Failed to write summary as JSON")?;
+        out.write_all(b"\n").context("Failed to write new line")?;
+    }
+    out.flush().context("Failed to flush summary")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amount::amount;
+
+    #[test]
+    fn write_summary_ndjson_should_write_object_per_line() {
+        let summaries = vec![
+            ClientSummary {
+                client: 1,
+                available: amount("130.1234"),
+                held: amount("0.0"),
+                total: amount("130.1234"),
+                locked: false,
+            },
+            ClientSummary {
+                client: 2,
+                available: amount("-5.5"),
+                held: amount("20.0"),
+                total: amount("14.5"),
+                locked: true,
+            },
+        ];
+
+        let mut out = vec![];
+        write_summary_ndjson(&summaries, &mut out).unwrap();
+
+        // `Decimal` amounts are serialized to JSON as strings
+        let expected = if cfg!(feature = "decimal") {
+            "{\"client\":1,\"available\":\"130.1234\",\"held\":\"0.0\",\"total\":\"130.1234\",\"locked\":false}\n\
+            {\"client\":2,\"available\":\"-5.5\",\"held\":\"20.0\",\"total\":\"14.5\",\"locked\":true}\n"
+        } else {
+            "{\"client\":1,\"available\":130.1234,\"held\":0.0,\"total\":130.1234,\"locked\":false}\n\
+            {\"client\":2,\"available\":-5.5,\"held\":20.0,\"total\":14.5,\"locked\":true}\n"
+        };
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+    }
+
+    #[test]
+    fn write_summary_ndjson_without_summaries_should_write_nothing() {
+        let mut out = vec![];
+        write_summary_ndjson(&[], &mut out).unwrap();
+
+        assert!(out.is_empty());
+    }
+}
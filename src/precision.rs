@@ -0,0 +1,19 @@
+/// Number of decimal places kept in the calculations by default
+pub const DECIMAL_PLACES: u32 = 4;
+
+/// Scale of the internal integer amounts, amount `1.0` is stored as `SCALE`
+pub const SCALE: i64 = 10_i64.pow(DECIMAL_PLACES);
+
+/// Maximal amount that can be represented by the internal integer amounts
+pub const MAX_AMOUNT: f64 = (i64::MAX / SCALE) as f64;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn max_amount_scaled_should_fit_in_i64() {
+        assert!((MAX_AMOUNT as i64).checked_mul(SCALE).is_some());
+        assert!((MAX_AMOUNT as i64 + 1).checked_mul(SCALE).is_none());
+    }
+}
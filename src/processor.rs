@@ -1,84 +1,404 @@
+// boolinator's `ok_or` on `bool` is used across the processor, a std method with the same name is only proposed
+#![allow(unstable_name_collisions)]
+
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::fmt::Debug;
+use std::marker::PhantomData;
 
 use boolinator::Boolinator;
 use itertools::Itertools;
+use num_traits::{CheckedAdd, PrimInt, Signed, Zero};
 
-use crate::models::{ClientId, ClientSummary, Transaction, TransactionId, TransactionType};
+use crate::amount::{self, Amount, RoundingMode};
+use crate::history::{HashMapHistoryStore, HistoryRecord, HistoryStore, TransactionStatus};
+use crate::metrics::{DisputeCounts, DisputeMetrics, StatusCounts};
+use crate::models::{
+    ClientId, ClientSummary, GrandTotals, Transaction, TransactionId, TransactionType,
+};
+use crate::precision;
 
-/// To ensure 4 digits precision, internally the calculations are using rounded integers
+/// To ensure the configured precision (4 digits by default), internally the calculations are using rounded integers
+/// `i64` is used by default, other signed integer types can be used via `AmountInt`
 type AmountType = i64;
-const PRECISION: f64 = 10000.0;
-fn f64_to_amount_type(v: f64) -> AmountType {
-    (v * PRECISION).round() as AmountType
+
+/// Integer type of the internal fixed point amounts, e.g. `i128` for huge amounts or `i32` for small ones
+pub trait AmountInt: PrimInt + CheckedAdd + Signed + Debug {}
+
+impl<T: PrimInt + CheckedAdd + Signed + Debug> AmountInt for T {}
+
+#[derive(Debug, Clone)]
+/// Options of the processor, see `TransactionsProcessorBuilder` for their description
+struct ProcessorConfig {
+    decimal_places: u32,
+    rounding_mode: RoundingMode,
+    allow_withdrawal_disputes: bool,
+    max_client_id: ClientId,
+    max_transaction_id: TransactionId,
+    allow_negative_balance: bool,
+    idempotent_deposits: bool,
+    verify_dispute_amounts: bool,
+    allow_deposits_when_locked: bool,
+    idempotent_resolve: bool,
+    max_transaction_amount: Option<Amount>,
+    active_only: bool,
+    check_invariants: bool,
+    explain_held_founds: bool,
+    max_dispute_reopens: Option<u32>,
+    clamp_negative_available: bool,
+}
+
+impl Default for ProcessorConfig {
+    fn default() -> Self {
+        Self {
+            decimal_places: precision::DECIMAL_PLACES,
+            rounding_mode: RoundingMode::default(),
+            allow_withdrawal_disputes: false,
+            max_client_id: ClientId::MAX,
+            max_transaction_id: TransactionId::MAX,
+            allow_negative_balance: true,
+            idempotent_deposits: false,
+            verify_dispute_amounts: false,
+            allow_deposits_when_locked: false,
+            idempotent_resolve: false,
+            max_transaction_amount: None,
+            active_only: false,
+            check_invariants: false,
+            explain_held_founds: false,
+            max_dispute_reopens: None,
+            clamp_negative_available: false,
+        }
+    }
+}
+
+impl ProcessorConfig {
+    /// Returns None if the amount does not fit in the integer type
+    fn amount_to_internal<A: AmountInt>(&self, v: Amount) -> Option<A> {
+        amount::to_scaled(v, self.decimal_places, self.rounding_mode)
+    }
+
+    fn internal_to_amount<A: AmountInt>(&self, v: A) -> Amount {
+        amount::from_scaled(v, self.decimal_places)
+    }
+
+    /// Returns error if the amount of deposit or withdrawal is over the configured limit
+    fn check_amount_limit(&self, amount: Amount) -> Result<(), TransactionProcessError> {
+        self.max_transaction_amount
+            .is_none_or(|max| amount <= max)
+            .ok_or(TransactionProcessError::AmountExceedsLimit)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Read-only view of the transaction status in client history together with its amount
+pub enum TxStatusView {
+    /// Transaction was successful and is valid, the amount excludes the part charged back by partial chargebacks
+    Processed { amount: Amount },
+    /// Transaction is under dispute, the amount is the disputed part
+    UnderDispute { amount: Amount },
+    /// Transaction is charged back
+    ChargeBack { amount: Amount },
+    /// Opening balance, it cannot be disputed
+    Settled { amount: Amount },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Read-only view of the transaction in client history
+pub struct TransactionRecordView {
+    pub transaction_id: TransactionId,
+    /// Deposits are positive, withdrawals negative
+    pub amount: Amount,
+    pub status: TransactionStatus,
+}
+
+impl TxStatusView {
+    fn new<A: AmountInt>(record: &HistoryRecord<A>, config: &ProcessorConfig) -> Self {
+        // Parts of the amount are positive, withdrawals are reported as negative
+        let signed = |part: A| {
+            config.internal_to_amount(if record.amount.is_negative() {
+                -part
+            } else {
+                part
+            })
+        };
+        match record.status {
+            TransactionStatus::Processed => TxStatusView::Processed {
+                amount: signed(record.amount.abs() - record.charged_back),
+            },
+            TransactionStatus::UnderDispute => TxStatusView::UnderDispute {
+                amount: signed(record.disputed),
+            },
+            TransactionStatus::ChargeBack => TxStatusView::ChargeBack {
+                amount: signed(record.charged_back),
+            },
+            TransactionStatus::Settled => TxStatusView::Settled {
+                amount: signed(record.amount),
+            },
+        }
+    }
 }
 
-fn amount_type_to_f64(v: AmountType) -> f64 {
-    (v as f64) / PRECISION
+#[derive(Clone)]
+/// ClientData contains current user state, its transactions are kept in the `HistoryStore`
+struct ClientData<A> {
+    /// Available founds, disputed withdrawals stay deducted from available until they are charged back
+    available: A,
+    /// Held founds (under dispute)
+    /// Held is never negative, for disputed withdrawals it is the amount returned to the client on chargeback
+    held: A,
+    /// Sum of all deposits, it bounds all the balances so checking it is enough to avoid overflows
+    deposited: A,
+    /// Number of deposits and withdrawals in the history of the client, opening balance counts as a single deposit
+    transactions_count: usize,
+    /// Set when a transaction is charged back, cleared only by `TransactionsProcessor::unlock`
+    locked: bool,
+    /// Administrative lock set by freeze and cleared by unfreeze transactions
+    frozen: bool,
+    disputes: DisputeCounts,
+    /// Set when a withdrawal leaves exactly zero available founds, cleared by the next deposit
+    drained_to_zero: bool,
 }
 
-/// Struct representing details of the transaction in client history
-struct TransactionRecord {
-    amount: AmountType,
-    status: TransactionStatus,
+impl<A: AmountInt> ClientData<A> {
+    /// Total equals available plus held (it does not overflow) and held does not exceed the total
+    /// The balances are integers, so no tolerance is needed in the comparison
+    /// Disputes of already withdrawn deposits break it, as they make available negative
+    fn satisfies_invariants(&self) -> bool {
+        self.available
+            .checked_add(&self.held)
+            .is_some_and(|total| self.held <= total)
+    }
 }
 
-#[derive(PartialEq)]
-/// Describes status of the transaction in user history
-enum TransactionStatus {
-    /// Transaction was successful and is valid, the founds are in available
-    Processed,
-    /// Transaction is under dispute, the founds are in held
-    UnderDispute,
-    /// Transaction is charged back, the transaction is ignored in held/total but client account is frozen
-    ChargeBack,
+/// Change of the history made by a transaction, written only after the balances are verified
+enum HistoryWrite<A> {
+    Insert(HistoryRecord<A>),
+    Update(HistoryRecord<A>),
 }
 
-#[derive(Default)]
-/// ClientData contains current user state
-struct ClientData {
-    /// All transactions already processed by user in their current state
-    transactions_history: HashMap<TransactionId, TransactionRecord>,
+impl<A: AmountInt> Default for ClientData<A> {
+    fn default() -> Self {
+        Self {
+            available: A::zero(),
+            held: A::zero(),
+            deposited: A::zero(),
+            transactions_count: 0,
+            locked: false,
+            frozen: false,
+            disputes: DisputeCounts::default(),
+            drained_to_zero: false,
+        }
+    }
 }
 
-impl ClientData {
-    /// Returns the available founds
-    fn available(&self) -> f64 {
-        amount_type_to_f64(
-            self.transactions_history
-                .values()
-                .filter(|t| t.status == TransactionStatus::Processed)
-                .map(|record| record.amount)
-                .sum(),
-        )
-    }
-
-    /// Returns the held founds (under dispute)
-    fn held(&self) -> f64 {
-        amount_type_to_f64(
-            self.transactions_history
-                .values()
-                .filter(|t| t.status == TransactionStatus::UnderDispute)
-                .map(|record| record.amount)
-                .sum(),
-        )
-    }
-
-    /// Returns true if there is at least one transaction with `TransactionStatus::ChargeBack` status
+impl<A> ClientData<A> {
+    /// Returns true if the account was locked by a chargeback or frozen
     fn locked(&self) -> bool {
-        self.transactions_history
-            .values()
-            .any(|t| t.status == TransactionStatus::ChargeBack)
+        self.locked || self.frozen
+    }
+}
+
+pub struct TransactionsProcessor<
+    A: AmountInt = AmountType,
+    S: HistoryStore<A> = HashMapHistoryStore<A>,
+> {
+    clients_data: HashMap<ClientId, ClientData<A>>,
+    history: S,
+    config: ProcessorConfig,
+    on_lock: Option<LockCallback>,
+}
+
+/// Callback invoked with the client and the charged back transaction when the account gets locked
+type LockCallback = Box<dyn FnMut(ClientId, TransactionId)>;
+
+impl Default for TransactionsProcessor {
+    fn default() -> Self {
+        TransactionsProcessorBuilder::new().build()
+    }
+}
+
+/// Builder of `TransactionsProcessor` with non default options
+/// Options not set explicitly behave the same as in `TransactionsProcessor::default()`
+/// Processor with other amount integer type is created with e.g. `TransactionsProcessorBuilder::<i128>::default()`
+pub struct TransactionsProcessorBuilder<A: AmountInt = AmountType> {
+    config: ProcessorConfig,
+    amount_type: PhantomData<A>,
+    on_lock: Option<LockCallback>,
+    expected_clients: usize,
+    expected_transactions_per_client: usize,
+}
+
+impl<A: AmountInt> Default for TransactionsProcessorBuilder<A> {
+    fn default() -> Self {
+        Self {
+            config: ProcessorConfig::default(),
+            amount_type: PhantomData,
+            on_lock: None,
+            expected_clients: 0,
+            expected_transactions_per_client: 0,
+        }
     }
 }
 
-#[derive(Default)]
-pub(crate) struct TransactionsProcessor {
-    clients_data: HashMap<ClientId, ClientData>,
+impl TransactionsProcessorBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<A: AmountInt> TransactionsProcessorBuilder<A> {
+    /// Number of decimal places kept in the calculations (4 by default)
+    /// Amounts are stored as integers scaled by `10^decimal_places`, so high values limit the maximal amount
+    pub fn decimal_places(mut self, decimal_places: u32) -> Self {
+        self.config.decimal_places = decimal_places;
+        self
+    }
+
+    /// Rounding of amounts with more decimal places than the precision (`RoundingMode::HalfUp` by default)
+    pub fn rounding_mode(mut self, rounding_mode: RoundingMode) -> Self {
+        self.config.rounding_mode = rounding_mode;
+        self
+    }
+
+    /// Allows disputing withdrawals (disabled by default)
+    /// A charged back withdrawal returns the withdrawn founds to the client and locks the account
+    pub fn allow_withdrawal_disputes(mut self, allow: bool) -> Self {
+        self.config.allow_withdrawal_disputes = allow;
+        self
+    }
+
+    /// Maximal accepted client id (`u64::MAX` by default), transactions of clients above it are rejected
+    pub fn max_client_id(mut self, max_client_id: ClientId) -> Self {
+        self.config.max_client_id = max_client_id;
+        self
+    }
+
+    /// Maximal accepted transaction id (`u64::MAX` by default), transactions above it are rejected
+    pub fn max_transaction_id(mut self, max_transaction_id: TransactionId) -> Self {
+        self.config.max_transaction_id = max_transaction_id;
+        self
+    }
+
+    /// Allows chargebacks leaving negative available founds (enabled by default)
+    /// When disabled such chargebacks fail and the transaction stays under dispute
+    pub fn allow_negative_balance(mut self, allow: bool) -> Self {
+        self.config.allow_negative_balance = allow;
+        self
+    }
+
+    /// Treats a repeated deposit with the same transaction id and amount as a retry and ignores it (disabled by default)
+    /// Repeated deposit with a different amount, or of a deposit under dispute or (partially) charged back since then,
+    /// still fails with `TransactionAlreadyProcessed` (`TransactionIdRetired` for charged back deposits)
+    pub fn idempotent_deposits(mut self, idempotent: bool) -> Self {
+        self.config.idempotent_deposits = idempotent;
+        self
+    }
+
+    /// Verifies the amount given in a dispute against the disputed transaction (disabled by default)
+    /// Disputes without amount are accepted as usual
+    pub fn verify_dispute_amounts(mut self, verify: bool) -> Self {
+        self.config.verify_dispute_amounts = verify;
+        self
+    }
+
+    /// Accepts deposits to locked (charged back or frozen) accounts, disabled by default
+    /// All other transactions on locked accounts are still rejected
+    pub fn allow_deposits_when_locked(mut self, allow: bool) -> Self {
+        self.config.allow_deposits_when_locked = allow;
+        self
+    }
+
+    /// Treats resolve of a processed transaction that is not under dispute as a no-op (disabled by default)
+    /// Resolve of unknown transaction is still rejected
+    pub fn idempotent_resolve(mut self, idempotent: bool) -> Self {
+        self.config.idempotent_resolve = idempotent;
+        self
+    }
+
+    /// Rejects deposits and withdrawals with amount over the limit, e.g. nonsense amounts of adversarial inputs
+    pub fn max_transaction_amount(mut self, max_transaction_amount: Amount) -> Self {
+        self.config.max_transaction_amount = Some(max_transaction_amount);
+        self
+    }
+
+    /// Skips in summaries the clients without any successful deposit or withdrawal, unless their account is locked
+    /// (e.g. clients whose only transaction was rejected)
+    pub fn active_only(mut self, active_only: bool) -> Self {
+        self.config.active_only = active_only;
+        self
+    }
+
+    /// Verifies the balances of the client resulting from each transaction before they are stored (disabled by default)
+    /// Total has to equal available plus held and held must not exceed the total, otherwise the transaction
+    /// is rejected with `InvariantViolation` and the account is left unchanged
+    /// E.g. disputes of deposits that were already withdrawn are rejected then, as held would exceed the total
+    pub fn check_invariants(mut self, check: bool) -> Self {
+        self.config.check_invariants = check;
+        self
+    }
+
+    /// Rejects withdrawals that would be covered by the founds held under dispute with `FundsHeldUnderDispute`
+    /// instead of `NotEnoughFoundsAvailable` (disabled by default), e.g. so UIs can explain the rejection
+    pub fn explain_held_founds(mut self, explain: bool) -> Self {
+        self.config.explain_held_founds = explain;
+        self
+    }
+
+    /// Limits how many times a resolved dispute of a transaction can be re-opened by another dispute
+    /// (unlimited by default), disputes over the limit fail with `DisputeReopenLimitReached`
+    pub fn max_dispute_reopens(mut self, max_dispute_reopens: u32) -> Self {
+        self.config.max_dispute_reopens = Some(max_dispute_reopens);
+        self
+    }
+
+    /// Reports negative available founds as zero in the summaries (disabled by default), e.g. when a dispute
+    /// arrives before the deposits covering it, the true balance is kept and the missing part is returned by `shortfall`
+    pub fn clamp_negative_available(mut self, clamp: bool) -> Self {
+        self.config.clamp_negative_available = clamp;
+        self
+    }
+
+    /// Sets callback invoked once each time a chargeback locks an account, e.g. for alerting
+    pub fn on_lock(mut self, callback: impl FnMut(ClientId, TransactionId) + 'static) -> Self {
+        self.on_lock = Some(Box::new(callback));
+        self
+    }
+
+    /// Pre-allocates memory for the expected number of clients and transactions of each client,
+    /// it only avoids rehashing of large inputs and does not limit the number of clients or transactions
+    /// Transactions per client are used only by the default `HashMapHistoryStore`
+    pub fn capacity(
+        mut self,
+        expected_clients: usize,
+        expected_transactions_per_client: usize,
+    ) -> Self {
+        self.expected_clients = expected_clients;
+        self.expected_transactions_per_client = expected_transactions_per_client;
+        self
+    }
+
+    pub fn build(self) -> TransactionsProcessor<A> {
+        let history = HashMapHistoryStore::with_capacity(
+            self.expected_clients,
+            self.expected_transactions_per_client,
+        );
+        self.build_with_store(history)
+    }
+
+    /// Builds the processor keeping the transactions history in the given store (e.g. disk backed for huge ledgers)
+    pub fn build_with_store<S: HistoryStore<A>>(self, history: S) -> TransactionsProcessor<A, S> {
+        TransactionsProcessor {
+            clients_data: HashMap::with_capacity(self.expected_clients),
+            history,
+            config: self.config,
+            on_lock: self.on_lock,
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, thiserror::Error)]
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
 /// Error type from processing the transactions
-pub(crate) enum TransactionProcessError {
+pub enum TransactionProcessError {
     #[error("Not enough founds")]
     NotEnoughFoundsAvailable,
 
@@ -88,6 +408,9 @@ pub(crate) enum TransactionProcessError {
     #[error("Non positive amount in transaction")]
     NonPositiveAmountInTransaction,
 
+    #[error("Amount in transaction rounds to zero")]
+    AmountRoundsToZero,
+
     #[error("Transaction not found")]
     TransactionNotFound,
 
@@ -105,117 +428,828 @@ pub(crate) enum TransactionProcessError {
 
     #[error("Transaction already processed")]
     TransactionAlreadyProcessed,
+
+    #[error("Client id out of range")]
+    ClientIdOutOfRange,
+
+    #[error("Transaction id out of range")]
+    TransactionIdOutOfRange,
+
+    #[error("Transaction to be disputed was charged back")]
+    CannotDisputeChargedBack,
+
+    #[error("Chargeback would make available founds negative")]
+    WouldOverdraw,
+
+    #[error("Amount out of range of the internal representation")]
+    AmountOutOfRange,
+
+    #[error("Amount of the dispute does not match the disputed transaction")]
+    DisputeAmountMismatch,
+
+    /// Charged back transaction ids are never reused, also after the account is unlocked
+    #[error("Transaction id was charged back and cannot be reused")]
+    TransactionIdRetired,
+
+    #[error("Amount of the transaction exceeds the limit")]
+    AmountExceedsLimit,
+
+    /// Withdrawal is over the available founds, but it would be covered together with the held founds
+    #[error("Not enough founds available, {held} founds are held under dispute")]
+    FundsHeldUnderDispute { held: Amount },
+
+    #[error("Dispute of the transaction was re-opened too many times")]
+    DisputeReopenLimitReached,
+
+    #[error("Opening balance is allowed only for clients without prior transactions")]
+    OpeningBalanceNotAllowed,
+
+    #[error("Transaction to be disputed is a settled opening balance")]
+    CannotDisputeSettled,
+
+    /// Internal error, the balances of the client would be inconsistent after processing the transaction
+    #[error("Balances of the client are inconsistent")]
+    InvariantViolation,
+}
+
+impl TransactionProcessError {
+    /// Returns true if the error may be caused by the order of transactions in the input,
+    /// e.g. dispute processed before the disputed deposit, so processing it again later could succeed
+    pub fn is_ordering_dependent(&self) -> bool {
+        match self {
+            TransactionProcessError::TransactionNotFound
+            | TransactionProcessError::TransactionNotUnderDispute => true,
+            TransactionProcessError::NotEnoughFoundsAvailable
+            | TransactionProcessError::MissingAmountValue
+            | TransactionProcessError::NonPositiveAmountInTransaction
+            | TransactionProcessError::AmountRoundsToZero
+            | TransactionProcessError::TransactionAlreadyUnderDispute
+            | TransactionProcessError::CannotDisputeWithdrawal
+            | TransactionProcessError::AccountLocked
+            | TransactionProcessError::TransactionAlreadyProcessed
+            | TransactionProcessError::ClientIdOutOfRange
+            | TransactionProcessError::TransactionIdOutOfRange
+            | TransactionProcessError::CannotDisputeChargedBack
+            | TransactionProcessError::WouldOverdraw
+            | TransactionProcessError::AmountOutOfRange
+            | TransactionProcessError::DisputeAmountMismatch
+            | TransactionProcessError::TransactionIdRetired
+            | TransactionProcessError::AmountExceedsLimit
+            | TransactionProcessError::FundsHeldUnderDispute { .. }
+            | TransactionProcessError::DisputeReopenLimitReached
+            | TransactionProcessError::OpeningBalanceNotAllowed
+            | TransactionProcessError::CannotDisputeSettled
+            | TransactionProcessError::InvariantViolation => false,
+        }
+    }
+}
+
+impl TransactionProcessError {
+    /// Returns stable machine readable identifier of the error, independent of its display message
+    pub fn code(&self) -> &'static str {
+        match self {
+            TransactionProcessError::NotEnoughFoundsAvailable => "not-enough-funds",
+            TransactionProcessError::MissingAmountValue => "missing-amount",
+            TransactionProcessError::NonPositiveAmountInTransaction => "non-positive-amount",
+            TransactionProcessError::AmountRoundsToZero => "amount-rounds-to-zero",
+            TransactionProcessError::TransactionNotFound => "transaction-not-found",
+            TransactionProcessError::TransactionAlreadyUnderDispute => "already-under-dispute",
+            TransactionProcessError::CannotDisputeWithdrawal => "cannot-dispute-withdrawal",
+            TransactionProcessError::TransactionNotUnderDispute => "not-under-dispute",
+            TransactionProcessError::AccountLocked => "account-locked",
+            TransactionProcessError::TransactionAlreadyProcessed => "already-processed",
+            TransactionProcessError::ClientIdOutOfRange => "client-id-out-of-range",
+            TransactionProcessError::TransactionIdOutOfRange => "transaction-id-out-of-range",
+            TransactionProcessError::CannotDisputeChargedBack => "cannot-dispute-charged-back",
+            TransactionProcessError::WouldOverdraw => "would-overdraw",
+            TransactionProcessError::AmountOutOfRange => "amount-out-of-range",
+            TransactionProcessError::DisputeAmountMismatch => "dispute-amount-mismatch",
+            TransactionProcessError::TransactionIdRetired => "transaction-id-retired",
+            TransactionProcessError::AmountExceedsLimit => "amount-exceeds-limit",
+            TransactionProcessError::FundsHeldUnderDispute { .. } => "funds-held-under-dispute",
+            TransactionProcessError::DisputeReopenLimitReached => "dispute-reopen-limit-reached",
+            TransactionProcessError::OpeningBalanceNotAllowed => "opening-balance-not-allowed",
+            TransactionProcessError::CannotDisputeSettled => "cannot-dispute-settled",
+            TransactionProcessError::InvariantViolation => "invariant-violation",
+        }
+    }
+}
+
+/// Transaction id of the synthetic settled deposit holding the opening balance loaded by
+/// `TransactionsProcessor::from_summary_csv`, deposits and withdrawals with this id are rejected as already processed
+pub const OPENING_BALANCE_TRANSACTION_ID: TransactionId = TransactionId::MAX;
+
+/// Checks that the transaction id was not used by the client yet
+fn ensure_transaction_id_unused<A>(
+    existing: Option<HistoryRecord<A>>,
+) -> Result<(), TransactionProcessError> {
+    match existing {
+        None => Ok(()),
+        Some(record) if record.status == TransactionStatus::ChargeBack => {
+            Err(TransactionProcessError::TransactionIdRetired)
+        }
+        Some(_) => Err(TransactionProcessError::TransactionAlreadyProcessed),
+    }
 }
 
 impl TransactionsProcessor {
+    /// Returns builder allowing to configure the processor
+    pub fn builder() -> TransactionsProcessorBuilder {
+        TransactionsProcessorBuilder::new()
+    }
+
+    /// Creates the processor with default options pre-sized for the expected number of clients
+    pub fn with_capacity(expected_clients: usize) -> Self {
+        Self::builder().capacity(expected_clients, 0).build()
+    }
+}
+
+impl<A: AmountInt, S: HistoryStore<A>> TransactionsProcessor<A, S> {
     /// Processes the transaction
-    pub(crate) fn process(
-        &mut self,
-        transaction: &Transaction,
-    ) -> Result<(), TransactionProcessError> {
+    pub fn process(&mut self, transaction: &Transaction) -> Result<(), TransactionProcessError> {
+        (transaction.client <= self.config.max_client_id)
+            .ok_or(TransactionProcessError::ClientIdOutOfRange)?;
+        (transaction.transaction_id <= self.config.max_transaction_id)
+            .ok_or(TransactionProcessError::TransactionIdOutOfRange)?;
+
         let client_entry = self.clients_data.entry(transaction.client).or_default();
-        // Return immediately if account is locked
-        (!client_entry.locked()).ok_or(TransactionProcessError::AccountLocked)?;
+        // Return immediately if account is locked, frozen account accepts only freeze and unfreeze
+        let deposit_allowed = self.config.allow_deposits_when_locked
+            && transaction.transaction_type == TransactionType::Deposit;
+        (!client_entry.locked || deposit_allowed).ok_or(TransactionProcessError::AccountLocked)?;
+        (!client_entry.frozen
+            || deposit_allowed
+            || matches!(
+                transaction.transaction_type,
+                TransactionType::Freeze | TransactionType::Unfreeze
+            ))
+        .ok_or(TransactionProcessError::AccountLocked)?;
 
+        // Balances are changed on a copy and the history change is deferred,
+        // both are written only after the balances are verified
+        let mut data = client_entry.clone();
+        let mut history_write = None;
         match transaction.transaction_type {
             TransactionType::Deposit => {
                 let amount = transaction
                     .amount
                     .ok_or(TransactionProcessError::MissingAmountValue)?;
 
-                (amount > 0.0).ok_or(TransactionProcessError::NonPositiveAmountInTransaction)?;
-
-                (!client_entry
-                    .transactions_history
-                    .contains_key(&transaction.transaction_id))
-                .ok_or(TransactionProcessError::TransactionAlreadyProcessed)?;
+                (amount > Amount::zero())
+                    .ok_or(TransactionProcessError::NonPositiveAmountInTransaction)?;
+                self.config.check_amount_limit(amount)?;
+                let amount: A = self
+                    .config
+                    .amount_to_internal(amount)
+                    .ok_or(TransactionProcessError::AmountOutOfRange)?;
+                (!amount.is_zero()).ok_or(TransactionProcessError::AmountRoundsToZero)?;
+                let existing = self
+                    .history
+                    .get(transaction.client, transaction.transaction_id);
+                // Id of charged back deposit is retired also for retries
+                existing
+                    .is_none_or(|record| record.status != TransactionStatus::ChargeBack)
+                    .ok_or(TransactionProcessError::TransactionIdRetired)?;
+                // Retry of already processed deposit is a no-op, unless the deposit was disputed or charged back since then
+                if self.config.idempotent_deposits
+                    && existing.is_some_and(|record| {
+                        record.amount == amount
+                            && record.status == TransactionStatus::Processed
+                            && record.charged_back.is_zero()
+                    })
+                {
+                    return Ok(());
+                }
+                let deposited = client_entry
+                    .deposited
+                    .checked_add(&amount)
+                    .ok_or(TransactionProcessError::AmountOutOfRange)?;
+                ensure_transaction_id_unused(existing)?;
 
-                client_entry.transactions_history.insert(
-                    transaction.transaction_id,
-                    TransactionRecord {
-                        amount: f64_to_amount_type(amount),
-                        status: TransactionStatus::Processed,
-                    },
-                );
+                history_write = Some(HistoryWrite::Insert(HistoryRecord {
+                    amount,
+                    status: TransactionStatus::Processed,
+                    disputed: A::zero(),
+                    charged_back: A::zero(),
+                    disputes: 0,
+                    sequence: data.transactions_count,
+                }));
+                data.transactions_count += 1;
+                data.deposited = deposited;
+                data.available = data.available + amount;
+                data.drained_to_zero = false;
             }
             TransactionType::Withdrawal => {
                 let amount = transaction
                     .amount
                     .ok_or(TransactionProcessError::MissingAmountValue)?;
-                (amount > 0.0).ok_or(TransactionProcessError::NonPositiveAmountInTransaction)?;
-                (amount <= client_entry.available())
-                    .ok_or(TransactionProcessError::NotEnoughFoundsAvailable)?;
-                (!client_entry
-                    .transactions_history
-                    .contains_key(&transaction.transaction_id))
-                .ok_or(TransactionProcessError::TransactionAlreadyProcessed)?;
+                (amount > Amount::zero())
+                    .ok_or(TransactionProcessError::NonPositiveAmountInTransaction)?;
+                self.config.check_amount_limit(amount)?;
+                let amount: A = self
+                    .config
+                    .amount_to_internal(amount)
+                    .ok_or(TransactionProcessError::AmountOutOfRange)?;
+                (!amount.is_zero()).ok_or(TransactionProcessError::AmountRoundsToZero)?;
+                if amount > data.available {
+                    let held = data.held;
+                    return Err(
+                        if self.config.explain_held_founds
+                            && held.is_positive()
+                            && amount <= data.available + held
+                        {
+                            TransactionProcessError::FundsHeldUnderDispute {
+                                held: self.config.internal_to_amount(held),
+                            }
+                        } else {
+                            TransactionProcessError::NotEnoughFoundsAvailable
+                        },
+                    );
+                }
+                ensure_transaction_id_unused(
+                    self.history
+                        .get(transaction.client, transaction.transaction_id),
+                )?;
 
                 // Withdrawals are saved as Transaction records with negative values
-                client_entry.transactions_history.insert(
-                    transaction.transaction_id,
-                    TransactionRecord {
-                        amount: f64_to_amount_type(-amount),
-                        status: TransactionStatus::Processed,
-                    },
-                );
+                history_write = Some(HistoryWrite::Insert(HistoryRecord {
+                    amount: -amount,
+                    status: TransactionStatus::Processed,
+                    disputed: A::zero(),
+                    charged_back: A::zero(),
+                    disputes: 0,
+                    sequence: data.transactions_count,
+                }));
+                data.transactions_count += 1;
+                data.available = data.available - amount;
+                data.drained_to_zero = data.available.is_zero();
             }
             TransactionType::Dispute => {
-                let entry = client_entry
-                    .transactions_history
-                    .get_mut(&transaction.transaction_id)
+                let record = self
+                    .history
+                    .get(transaction.client, transaction.transaction_id)
                     .ok_or(TransactionProcessError::TransactionNotFound)?;
-                (entry.status == TransactionStatus::Processed)
+                (record.status != TransactionStatus::ChargeBack)
+                    .ok_or(TransactionProcessError::CannotDisputeChargedBack)?;
+                (record.status != TransactionStatus::Settled)
+                    .ok_or(TransactionProcessError::CannotDisputeSettled)?;
+                // Disputed withdrawals are stored as negative, the dispute amount is always positive
+                // Part charged back by a partial chargeback cannot be disputed again
+                let whole = record.amount.abs() - record.charged_back;
+                // Dispute without amount is for the whole rest of the transaction, with smaller amount only for its part
+                let disputed = match transaction.amount {
+                    Some(amount) => {
+                        // Zero amount is rejected as in deposits and withdrawals, it is not a dispute of the whole transaction
+                        (amount > Amount::zero())
+                            .ok_or(TransactionProcessError::NonPositiveAmountInTransaction)?;
+                        if self.config.verify_dispute_amounts {
+                            (self.config.amount_to_internal(amount) == Some(whole))
+                                .ok_or(TransactionProcessError::DisputeAmountMismatch)?;
+                            whole
+                        } else {
+                            let disputed: A = self
+                                .config
+                                .amount_to_internal(amount)
+                                .ok_or(TransactionProcessError::AmountOutOfRange)?;
+                            (!disputed.is_zero())
+                                .ok_or(TransactionProcessError::AmountRoundsToZero)?;
+                            (disputed <= whole)
+                                .ok_or(TransactionProcessError::DisputeAmountMismatch)?;
+                            disputed
+                        }
+                    }
+                    None => whole,
+                };
+                (record.status == TransactionStatus::Processed)
                     .ok_or(TransactionProcessError::TransactionAlreadyUnderDispute)?;
-                (entry.amount > 0).ok_or(TransactionProcessError::CannotDisputeWithdrawal)?;
-                entry.status = TransactionStatus::UnderDispute
+                (record.amount.is_positive() || self.config.allow_withdrawal_disputes)
+                    .ok_or(TransactionProcessError::CannotDisputeWithdrawal)?;
+                // The first dispute is not a re-open
+                self.config
+                    .max_dispute_reopens
+                    .is_none_or(|max| record.disputes <= max)
+                    .ok_or(TransactionProcessError::DisputeReopenLimitReached)?;
+                history_write = Some(HistoryWrite::Update(HistoryRecord {
+                    status: TransactionStatus::UnderDispute,
+                    disputed,
+                    disputes: record.disputes + 1,
+                    ..record
+                }));
+                // Disputed withdrawal stays deducted from available
+                if record.amount.is_positive() {
+                    data.available = data.available - disputed;
+                }
+                data.held = data.held + disputed;
+                data.disputes.opened += 1;
             }
             TransactionType::Resolve => {
-                let entry = client_entry
-                    .transactions_history
-                    .get_mut(&transaction.transaction_id)
+                let record = self
+                    .history
+                    .get(transaction.client, transaction.transaction_id)
                     .ok_or(TransactionProcessError::TransactionNotFound)?;
-                (entry.status == TransactionStatus::UnderDispute)
+                // Repeated resolve is a no-op
+                if self.config.idempotent_resolve && record.status == TransactionStatus::Processed {
+                    return Ok(());
+                }
+                (record.status == TransactionStatus::UnderDispute)
                     .ok_or(TransactionProcessError::TransactionNotUnderDispute)?;
-                entry.status = TransactionStatus::Processed
+                history_write = Some(HistoryWrite::Update(HistoryRecord {
+                    status: TransactionStatus::Processed,
+                    disputed: A::zero(),
+                    ..record
+                }));
+                if record.amount.is_positive() {
+                    data.available = data.available + record.disputed;
+                }
+                data.held = data.held - record.disputed;
+                data.disputes.resolved += 1;
             }
             TransactionType::Chargeback => {
-                let entry = client_entry
-                    .transactions_history
-                    .get_mut(&transaction.transaction_id)
+                let record = self
+                    .history
+                    .get(transaction.client, transaction.transaction_id)
                     .ok_or(TransactionProcessError::TransactionNotFound)?;
-                (entry.status == TransactionStatus::UnderDispute)
+                (record.status == TransactionStatus::UnderDispute)
                     .ok_or(TransactionProcessError::TransactionNotUnderDispute)?;
-                entry.status = TransactionStatus::ChargeBack
+                // Disputed part of deposit is already excluded from available, disputed part of withdrawal is returned to it
+                // The undisputed rest of a partially disputed transaction stays processed
+                let available_after_chargeback = if record.amount.is_negative() {
+                    data.available + record.disputed
+                } else {
+                    data.available
+                };
+                (!available_after_chargeback.is_negative() || self.config.allow_negative_balance)
+                    .ok_or(TransactionProcessError::WouldOverdraw)?;
+                let charged_back = record.charged_back + record.disputed;
+                // The undisputed rest of a partially charged back transaction can still be disputed
+                let status = if charged_back == record.amount.abs() {
+                    TransactionStatus::ChargeBack
+                } else {
+                    TransactionStatus::Processed
+                };
+                history_write = Some(HistoryWrite::Update(HistoryRecord {
+                    status,
+                    disputed: A::zero(),
+                    charged_back,
+                    ..record
+                }));
+                data.available = available_after_chargeback;
+                data.held = data.held - record.disputed;
+                data.locked = true;
+                data.disputes.charged_back += 1;
+            }
+            TransactionType::Freeze => data.frozen = true,
+            TransactionType::Unfreeze => data.frozen = false,
+            TransactionType::OpeningBalance => {
+                let amount = transaction
+                    .amount
+                    .ok_or(TransactionProcessError::MissingAmountValue)?;
+                (amount > Amount::zero())
+                    .ok_or(TransactionProcessError::NonPositiveAmountInTransaction)?;
+                self.config.check_amount_limit(amount)?;
+                let amount: A = self
+                    .config
+                    .amount_to_internal(amount)
+                    .ok_or(TransactionProcessError::AmountOutOfRange)?;
+                (!amount.is_zero()).ok_or(TransactionProcessError::AmountRoundsToZero)?;
+                (data.transactions_count == 0 && data.available.is_zero() && data.held.is_zero())
+                    .ok_or(TransactionProcessError::OpeningBalanceNotAllowed)?;
+                // Opening balance is kept in the history to reserve its id, it is settled so it cannot be disputed
+                history_write = Some(HistoryWrite::Insert(HistoryRecord {
+                    amount,
+                    status: TransactionStatus::Settled,
+                    disputed: A::zero(),
+                    charged_back: A::zero(),
+                    disputes: 0,
+                    sequence: data.transactions_count,
+                }));
+                data.transactions_count += 1;
+                data.deposited = amount;
+                data.available = amount;
             }
         }
 
+        let consistent = data.satisfies_invariants();
+        debug_assert!(
+            !data.held.is_negative(),
+            "Negative held founds after transaction {transaction:?}"
+        );
+        (consistent || !self.config.check_invariants)
+            .ok_or(TransactionProcessError::InvariantViolation)?;
+        match history_write {
+            Some(HistoryWrite::Insert(record)) => {
+                self.history
+                    .insert(transaction.client, transaction.transaction_id, record)
+            }
+            Some(HistoryWrite::Update(record)) => {
+                self.history
+                    .update(transaction.client, transaction.transaction_id, record)
+            }
+            None => {}
+        }
+        let newly_locked = data.locked && !client_entry.locked;
+        *client_entry = data;
+        if newly_locked {
+            if let Some(on_lock) = &mut self.on_lock {
+                on_lock(transaction.client, transaction.transaction_id);
+            }
+        }
         Ok(())
     }
 
+    /// Processes the correcting transaction and returns the updated summary of all client accounts
+    /// A rejected transaction leaves the summary unchanged (no account is created for a new client)
+    pub fn apply_and_summarize(
+        &mut self,
+        transaction: &Transaction,
+    ) -> Result<Vec<ClientSummary>, TransactionProcessError> {
+        self.process_or_forget_client(transaction)?;
+        Ok(self.summary())
+    }
+
+    /// Processes the transaction and returns the updated summary of the affected client
+    /// A rejected transaction leaves the processor unchanged (no account is created for a new client)
+    pub fn process_and_get(
+        &mut self,
+        transaction: &Transaction,
+    ) -> Result<ClientSummary, TransactionProcessError> {
+        self.process_or_forget_client(transaction)?;
+        let data = &self.clients_data[&transaction.client];
+        Ok(self.client_summary(transaction.client, data))
+    }
+
+    /// Processes the transaction, removing the account created for a new client if it is rejected
+    fn process_or_forget_client(
+        &mut self,
+        transaction: &Transaction,
+    ) -> Result<(), TransactionProcessError> {
+        let known_client = self.clients_data.contains_key(&transaction.client);
+        self.process(transaction).inspect_err(|_| {
+            if !known_client {
+                self.clients_data.remove(&transaction.client);
+            }
+        })
+    }
+
     /// Returns summary of client accounts after processing transactions
-    pub(crate) fn summary(&self) -> Vec<ClientSummary> {
+    pub fn summary(&self) -> Vec<ClientSummary> {
+        self.unsorted_summary()
+            // Sorting added for consistent outputs, not strictly needed but simplifies the tests
+            .sorted_by_key(|summary| summary.client)
+            .collect()
+    }
+
+    /// Returns summary of client accounts sorted with the given comparator, e.g. by total descending
+    pub fn summary_sorted_by<F>(&self, cmp: F) -> Vec<ClientSummary>
+    where
+        F: FnMut(&ClientSummary, &ClientSummary) -> Ordering,
+    {
+        self.unsorted_summary().sorted_by(cmp).collect()
+    }
+
+    fn unsorted_summary(&self) -> impl Iterator<Item = ClientSummary> + '_ {
         self.clients_data
             .iter()
-            .map(|(client_id, data)| {
-                let available = data.available();
-                let held = data.held();
-                ClientSummary {
-                    client: *client_id,
-                    available,
-                    held,
-                    total: held + available,
-                    locked: data.locked(),
-                }
+            .filter(|(_, data)| {
+                !self.config.active_only || data.transactions_count > 0 || data.locked()
+            })
+            .map(|(client_id, data)| self.client_summary(*client_id, data))
+    }
+
+    fn client_summary(&self, client: ClientId, data: &ClientData<A>) -> ClientSummary {
+        let available = self.reported_available(data);
+        ClientSummary {
+            client,
+            available: self.config.internal_to_amount(available),
+            held: self.config.internal_to_amount(data.held),
+            total: self.config.internal_to_amount(data.held + available),
+            locked: data.locked(),
+        }
+    }
+
+    /// Returns the available founds of the client as reported in the summaries, see `clamp_negative_available`
+    fn reported_available(&self, data: &ClientData<A>) -> A {
+        if self.config.clamp_negative_available {
+            data.available.max(A::zero())
+        } else {
+            data.available
+        }
+    }
+
+    /// Returns how much the available founds of the client are below zero (zero if they are not), None for unknown clients
+    pub fn shortfall(&self, client: ClientId) -> Option<Amount> {
+        self.clients_data.get(&client).map(|data| {
+            self.config
+                .internal_to_amount((-data.available).max(A::zero()))
+        })
+    }
+
+    /// Returns the summary of the client after the transaction without changing the processor,
+    /// e.g. to check the result of a chargeback before applying it
+    /// Only the state of the affected client is copied, the lock callback is not invoked
+    pub fn simulate(
+        &self,
+        transaction: &Transaction,
+    ) -> Result<ClientSummary, TransactionProcessError> {
+        let client = transaction.client;
+        let mut sandbox = self.sandbox(client);
+        sandbox.process(transaction)?;
+        let data = &sandbox.clients_data[&client];
+        Ok(sandbox.client_summary(client, data))
+    }
+
+    /// Returns processor with a copy of the state of the client, without the lock callback
+    fn sandbox(&self, client: ClientId) -> TransactionsProcessor<A> {
+        let mut history = HashMapHistoryStore::default();
+        for (transaction_id, record) in self.history.client_records(client) {
+            history.insert(client, transaction_id, record);
+        }
+        TransactionsProcessor {
+            clients_data: self
+                .clients_data
+                .get(&client)
+                .map(|data| HashMap::from([(client, data.clone())]))
+                .unwrap_or_default(),
+            history,
+            config: self.config.clone(),
+            on_lock: None,
+        }
+    }
+
+    /// Disputes all the given transactions of the client, or none of them if any dispute fails
+    /// Returns the first failing transaction with its error, the processor is not changed then
+    pub fn dispute_batch(
+        &mut self,
+        client: ClientId,
+        transaction_ids: &[TransactionId],
+    ) -> Result<(), (TransactionId, TransactionProcessError)> {
+        let disputes = transaction_ids
+            .iter()
+            .map(|transaction_id| Transaction {
+                transaction_type: TransactionType::Dispute,
+                client,
+                transaction_id: *transaction_id,
+                amount: None,
+                timestamp: None,
+            })
+            .collect::<Vec<_>>();
+        // Disputes are validated one after another on a copy, so e.g. a transaction repeated in the batch fails
+        let mut sandbox = self.sandbox(client);
+        for dispute in &disputes {
+            sandbox
+                .process(dispute)
+                .map_err(|err| (dispute.transaction_id, err))?;
+        }
+        for dispute in &disputes {
+            self.process(dispute)
+                .map_err(|err| (dispute.transaction_id, err))?;
+        }
+        Ok(())
+    }
+
+    /// Returns true if no transaction was processed yet, cheaper than checking the summary
+    pub fn is_empty(&self) -> bool {
+        self.clients_data.is_empty()
+    }
+
+    /// Returns number of clients seen by the processor, including clients whose transactions were all rejected
+    pub fn client_count(&self) -> usize {
+        self.clients_data.len()
+    }
+
+    /// Returns number of unlocked accounts with non zero balance
+    pub fn active_account_count(&self) -> usize {
+        self.clients_data
+            .values()
+            .filter(|data| !data.locked() && (!data.available.is_zero() || !data.held.is_zero()))
+            .count()
+    }
+
+    /// Returns the amount the client could withdraw right now, None for unknown clients
+    /// Locked accounts and accounts with negative available founds have nothing to withdraw (zero)
+    pub fn withdrawable(&self, client: ClientId) -> Option<Amount> {
+        self.clients_data.get(&client).map(|data| {
+            if data.locked() || data.available.is_negative() {
+                Amount::zero()
+            } else {
+                self.config.internal_to_amount(data.available)
+            }
+        })
+    }
+
+    /// Returns sorted ids of clients with locked accounts (charged back or frozen)
+    pub fn locked_clients(&self) -> Vec<ClientId> {
+        self.clients_data
+            .iter()
+            .filter(|(_, data)| data.locked())
+            .map(|(client, _)| *client)
+            .sorted()
+            .collect()
+    }
+
+    /// Returns true if the last withdrawal of the client left exactly zero available founds
+    /// and there was no deposit since then (e.g. for dormancy handling)
+    pub fn is_drained_to_zero(&self, client: ClientId) -> bool {
+        self.clients_data
+            .get(&client)
+            .is_some_and(|data| data.drained_to_zero)
+    }
+
+    /// Unlocks the account locked by a chargeback (e.g. after a manual review)
+    /// The charged back transactions stay in the history and are not counted in the balances,
+    /// their ids are retired and cannot be used by new deposits or withdrawals
+    pub fn unlock(&mut self, client: ClientId) {
+        if let Some(data) = self.clients_data.get_mut(&client) {
+            data.locked = false;
+        }
+    }
+
+    /// Sets the opening balance of a new client from a summary of the previous period
+    /// The balance is kept in the history as a settled deposit of the total with `OPENING_BALANCE_TRANSACTION_ID`,
+    /// so it cannot be disputed
+    pub(crate) fn open_balance(
+        &mut self,
+        summary: &ClientSummary,
+    ) -> Result<(), TransactionProcessError> {
+        let available: A = self
+            .config
+            .amount_to_internal(summary.available)
+            .ok_or(TransactionProcessError::AmountOutOfRange)?;
+        let held: A = self
+            .config
+            .amount_to_internal(summary.held)
+            .ok_or(TransactionProcessError::AmountOutOfRange)?;
+        let deposited = available
+            .checked_add(&held)
+            .ok_or(TransactionProcessError::AmountOutOfRange)?;
+        (!self.clients_data.contains_key(&summary.client))
+            .ok_or(TransactionProcessError::TransactionAlreadyProcessed)?;
+        self.history.insert(
+            summary.client,
+            OPENING_BALANCE_TRANSACTION_ID,
+            HistoryRecord {
+                amount: deposited,
+                status: TransactionStatus::Settled,
+                disputed: A::zero(),
+                charged_back: A::zero(),
+                disputes: 0,
+                sequence: 0,
+            },
+        );
+        self.clients_data.insert(
+            summary.client,
+            ClientData {
+                available,
+                held,
+                deposited,
+                // Opening balance counts as a single settled deposit
+                transactions_count: 1,
+                locked: summary.locked,
+                ..ClientData::default()
+            },
+        );
+        Ok(())
+    }
+
+    /// Returns available, held and total of the client as the internal integer amounts
+    pub(crate) fn minor_units(&self, client: ClientId) -> (A, A, A) {
+        self.clients_data
+            .get(&client)
+            .map_or((A::zero(), A::zero(), A::zero()), |data| {
+                let available = self.reported_available(data);
+                (available, data.held, available + data.held)
+            })
+    }
+
+    /// Returns balances summed across all clients, calculated on the internal integer amounts
+    /// The sums saturate at the limits of the amount type
+    pub fn grand_totals(&self) -> GrandTotals {
+        let (available, held, locked_count) = self.clients_data.values().fold(
+            (A::zero(), A::zero(), 0),
+            |(available, held, locked_count), data| {
+                (
+                    available.saturating_add(self.reported_available(data)),
+                    held.saturating_add(data.held),
+                    locked_count + usize::from(data.locked()),
+                )
+            },
+        );
+        GrandTotals {
+            available: self.config.internal_to_amount(available),
+            held: self.config.internal_to_amount(held),
+            total: self
+                .config
+                .internal_to_amount(available.saturating_add(held)),
+            locked_count,
+        }
+    }
+
+    /// Returns available minus held of the client, calculated on the internal integer amounts
+    pub(crate) fn net(&self, client: ClientId) -> Amount {
+        self.clients_data
+            .get(&client)
+            .map_or(Amount::zero(), |data| {
+                self.config
+                    .internal_to_amount(self.reported_available(data) - data.held)
+            })
+    }
+
+    /// Returns the status of the given transaction in client history, None if it was never processed
+    pub fn transaction_status(
+        &self,
+        client: ClientId,
+        transaction_id: TransactionId,
+    ) -> Option<TxStatusView> {
+        self.history
+            .get(client, transaction_id)
+            .map(|record| TxStatusView::new(&record, &self.config))
+    }
+
+    /// Returns counts of opened, resolved and charged back disputes, in total and per client
+    pub fn dispute_metrics(&self) -> DisputeMetrics {
+        let mut metrics = DisputeMetrics::default();
+        for (client, data) in &self.clients_data {
+            if data.disputes != DisputeCounts::default() {
+                metrics.total += data.disputes;
+                metrics.per_client.insert(*client, data.disputes);
+            }
+        }
+        metrics
+    }
+
+    /// Returns all deposits and withdrawals of the client in the order they were processed
+    pub fn client_history(&self, client: ClientId) -> Vec<TransactionRecordView> {
+        self.history
+            .client_records(client)
+            .into_iter()
+            .sorted_by_key(|(_, record)| record.sequence)
+            .map(|(transaction_id, record)| TransactionRecordView {
+                transaction_id,
+                amount: self.config.internal_to_amount(record.amount),
+                status: record.status,
             })
-            // Sorting added for consistent outputs, not strictly needed but simplifies the tests
-            .sorted_by_key(|summary| summary.client)
             .collect()
     }
+
+    /// Returns the number of deposits and withdrawals of the client in each status, None for unknown clients
+    pub fn status_counts(&self, client: ClientId) -> Option<StatusCounts> {
+        self.clients_data.get(&client)?;
+        let mut counts = StatusCounts::default();
+        for (_, record) in self.history.client_records(client) {
+            match record.status {
+                TransactionStatus::Processed => counts.processed += 1,
+                TransactionStatus::UnderDispute => counts.under_dispute += 1,
+                TransactionStatus::ChargeBack => counts.charged_back += 1,
+                TransactionStatus::Settled => counts.settled += 1,
+            }
+        }
+        Some(counts)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
     use super::*;
+    use crate::amount::amount;
+
+    /// Processes the transactions given as (type, client, transaction id, amount), all of them have to succeed
+    fn process_all<S: HistoryStore<AmountType>>(
+        processor: &mut TransactionsProcessor<AmountType, S>,
+        transactions: &[(TransactionType, ClientId, TransactionId, Option<Amount>)],
+    ) {
+        for (transaction_type, client, transaction_id, amount) in transactions {
+            processor
+                .process(&Transaction {
+                    transaction_type: *transaction_type,
+                    client: *client,
+                    transaction_id: *transaction_id,
+                    amount: *amount,
+                    timestamp: None,
+                })
+                .unwrap();
+        }
+    }
+
+    fn assert_summary_approx_eq(actual: Vec<ClientSummary>, expected: Vec<ClientSummary>) {
+        assert_eq!(actual.len(), expected.len(), "{actual:?} != {expected:?}");
+        for (a, e) in actual.iter().zip(&expected) {
+            assert!(a.approx_eq(e, 4), "{actual:?} != {expected:?}");
+        }
+    }
+
+    /// Simple xorshift generator of numbers in range `0..bound`, so the sequence is the same in every run
+    fn xorshift(seed: u64) -> impl FnMut(u64) -> u64 {
+        let mut state = seed;
+        move |bound| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % bound
+        }
+    }
 
     #[test]
     fn without_transactions_should_return_empty_summary() {
@@ -232,16 +1266,17 @@ mod tests {
                 transaction_type: TransactionType::Deposit,
                 client: 1,
                 transaction_id: 1,
-                amount: Some(10.0),
+                amount: Some(amount("10.0")),
+                timestamp: None,
             })
             .unwrap();
         assert_eq!(
             processor.summary(),
             vec![ClientSummary {
                 client: 1,
-                available: 10.0,
-                held: 0.0,
-                total: 10.0,
+                available: amount("10.0"),
+                held: amount("0.0"),
+                total: amount("10.0"),
                 locked: false,
             }]
         );
@@ -251,19 +1286,20 @@ mod tests {
                 transaction_type: TransactionType::Deposit,
                 client: 1,
                 transaction_id: 2,
-                amount: Some(123.123),
+                amount: Some(amount("123.123")),
+                timestamp: None,
             })
             .unwrap();
 
-        assert_eq!(
+        assert_summary_approx_eq(
             processor.summary(),
             vec![ClientSummary {
                 client: 1,
-                available: 133.123,
-                held: 0.0,
-                total: 133.123,
+                available: amount("133.123"),
+                held: amount("0.0"),
+                total: amount("133.123"),
                 locked: false,
-            }]
+            }],
         );
     }
 
@@ -275,7 +1311,8 @@ mod tests {
                 transaction_type: TransactionType::Deposit,
                 client: 1,
                 transaction_id: 1,
-                amount: Some(-10.0),
+                amount: Some(amount("-10.0")),
+                timestamp: None,
             })
             .unwrap_err();
         assert_eq!(err, TransactionProcessError::NonPositiveAmountInTransaction);
@@ -283,23 +1320,60 @@ mod tests {
             processor.summary(),
             vec![ClientSummary {
                 client: 1,
-                available: 0.0,
-                held: 0.0,
-                total: 0.0,
+                available: amount("0.0"),
+                held: amount("0.0"),
+                total: amount("0.0"),
                 locked: false,
             }]
         );
     }
 
     #[test]
-    fn deposit_the_same_transaction_twice_should_fail() {
+    fn deposit_amount_rounding_to_zero_should_fail_and_not_use_transaction_id() {
+        let mut processor = TransactionsProcessor::default();
+        let err = processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Deposit,
+                client: 1,
+                transaction_id: 1,
+                amount: Some(amount("0.00001")),
+                timestamp: None,
+            })
+            .unwrap_err();
+        assert_eq!(err, TransactionProcessError::AmountRoundsToZero);
+        assert_eq!(processor.transaction_status(1, 1), None);
+
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Deposit,
+                client: 1,
+                transaction_id: 1,
+                amount: Some(amount("10.0")),
+                timestamp: None,
+            })
+            .unwrap();
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary {
+                client: 1,
+                available: amount("10.0"),
+                held: amount("0.0"),
+                total: amount("10.0"),
+                locked: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn deposit_the_same_transaction_twice_should_fail() {
         let mut processor = TransactionsProcessor::default();
         processor
             .process(&Transaction {
                 transaction_type: TransactionType::Deposit,
                 client: 1,
                 transaction_id: 1,
-                amount: Some(10.0),
+                amount: Some(amount("10.0")),
+                timestamp: None,
             })
             .unwrap();
         let err = processor
@@ -307,7 +1381,8 @@ mod tests {
                 transaction_type: TransactionType::Deposit,
                 client: 1,
                 transaction_id: 1,
-                amount: Some(10.0),
+                amount: Some(amount("10.0")),
+                timestamp: None,
             })
             .unwrap_err();
         assert_eq!(err, TransactionProcessError::TransactionAlreadyProcessed);
@@ -315,9 +1390,9 @@ mod tests {
             processor.summary(),
             vec![ClientSummary {
                 client: 1,
-                available: 10.0,
-                held: 0.0,
-                total: 10.0,
+                available: amount("10.0"),
+                held: amount("0.0"),
+                total: amount("10.0"),
                 locked: false,
             }]
         );
@@ -332,6 +1407,7 @@ mod tests {
                 client: 1,
                 transaction_id: 1,
                 amount: None,
+                timestamp: None,
             })
             .unwrap_err();
         assert_eq!(err, TransactionProcessError::MissingAmountValue);
@@ -339,9 +1415,9 @@ mod tests {
             processor.summary(),
             vec![ClientSummary {
                 client: 1,
-                available: 0.0,
-                held: 0.0,
-                total: 0.0,
+                available: amount("0.0"),
+                held: amount("0.0"),
+                total: amount("0.0"),
                 locked: false,
             }]
         );
@@ -356,7 +1432,8 @@ mod tests {
                 transaction_type: TransactionType::Deposit,
                 client: 1,
                 transaction_id: 1,
-                amount: Some(23.0),
+                amount: Some(amount("23.0")),
+                timestamp: None,
             })
             .unwrap();
 
@@ -365,7 +1442,8 @@ mod tests {
                 transaction_type: TransactionType::Deposit,
                 client: 6,
                 transaction_id: 2,
-                amount: Some(123.123),
+                amount: Some(amount("123.123")),
+                timestamp: None,
             })
             .unwrap();
 
@@ -374,16 +1452,16 @@ mod tests {
             vec![
                 ClientSummary {
                     client: 1,
-                    available: 23.0,
-                    held: 0.0,
-                    total: 23.0,
+                    available: amount("23.0"),
+                    held: amount("0.0"),
+                    total: amount("23.0"),
                     locked: false,
                 },
                 ClientSummary {
                     client: 6,
-                    available: 123.123,
-                    held: 0.0,
-                    total: 123.123,
+                    available: amount("123.123"),
+                    held: amount("0.0"),
+                    total: amount("123.123"),
                     locked: false,
                 }
             ]
@@ -399,7 +1477,8 @@ mod tests {
                 transaction_type: TransactionType::Deposit,
                 client: 1,
                 transaction_id: 1,
-                amount: Some(100.0),
+                amount: Some(amount("100.0")),
+                timestamp: None,
             })
             .unwrap();
 
@@ -408,7 +1487,8 @@ mod tests {
                 transaction_type: TransactionType::Withdrawal,
                 client: 1,
                 transaction_id: 2,
-                amount: Some(25.0),
+                amount: Some(amount("25.0")),
+                timestamp: None,
             })
             .unwrap();
 
@@ -416,9 +1496,9 @@ mod tests {
             processor.summary(),
             vec![ClientSummary {
                 client: 1,
-                available: 75.0,
-                held: 0.0,
-                total: 75.0,
+                available: amount("75.0"),
+                held: amount("0.0"),
+                total: amount("75.0"),
                 locked: false,
             }]
         );
@@ -428,7 +1508,8 @@ mod tests {
                 transaction_type: TransactionType::Withdrawal,
                 client: 1,
                 transaction_id: 3,
-                amount: Some(75.0),
+                amount: Some(amount("75.0")),
+                timestamp: None,
             })
             .unwrap();
 
@@ -436,9 +1517,9 @@ mod tests {
             processor.summary(),
             vec![ClientSummary {
                 client: 1,
-                available: 0.0,
-                held: 0.0,
-                total: 0.0,
+                available: amount("0.0"),
+                held: amount("0.0"),
+                total: amount("0.0"),
                 locked: false,
             }]
         );
@@ -454,7 +1535,8 @@ mod tests {
                 transaction_type: TransactionType::Withdrawal,
                 client: 1,
                 transaction_id: 2,
-                amount: Some(25.0),
+                amount: Some(amount("25.0")),
+                timestamp: None,
             })
             .unwrap_err();
         assert_eq!(err, TransactionProcessError::NotEnoughFoundsAvailable);
@@ -463,9 +1545,9 @@ mod tests {
             processor.summary(),
             vec![ClientSummary {
                 client: 1,
-                available: 0.0,
-                held: 0.0,
-                total: 0.0,
+                available: amount("0.0"),
+                held: amount("0.0"),
+                total: amount("0.0"),
                 locked: false,
             }]
         );
@@ -475,7 +1557,8 @@ mod tests {
                 transaction_type: TransactionType::Deposit,
                 client: 1,
                 transaction_id: 2,
-                amount: Some(20.0),
+                amount: Some(amount("20.0")),
+                timestamp: None,
             })
             .unwrap();
 
@@ -484,7 +1567,8 @@ mod tests {
                 transaction_type: TransactionType::Withdrawal,
                 client: 1,
                 transaction_id: 3,
-                amount: Some(20.0001),
+                amount: Some(amount("20.0001")),
+                timestamp: None,
             })
             .unwrap_err();
         assert_eq!(err, TransactionProcessError::NotEnoughFoundsAvailable);
@@ -493,9 +1577,9 @@ mod tests {
             processor.summary(),
             vec![ClientSummary {
                 client: 1,
-                available: 20.0,
-                held: 0.0,
-                total: 20.0,
+                available: amount("20.0"),
+                held: amount("0.0"),
+                total: amount("20.0"),
                 locked: false,
             }]
         );
@@ -509,7 +1593,8 @@ mod tests {
                 transaction_type: TransactionType::Deposit,
                 client: 1,
                 transaction_id: 1,
-                amount: Some(100.0),
+                amount: Some(amount("100.0")),
+                timestamp: None,
             })
             .unwrap();
         let err = processor
@@ -517,7 +1602,8 @@ mod tests {
                 transaction_type: TransactionType::Withdrawal,
                 client: 1,
                 transaction_id: 1,
-                amount: Some(-10.0),
+                amount: Some(amount("-10.0")),
+                timestamp: None,
             })
             .unwrap_err();
         assert_eq!(err, TransactionProcessError::NonPositiveAmountInTransaction);
@@ -525,9 +1611,9 @@ mod tests {
             processor.summary(),
             vec![ClientSummary {
                 client: 1,
-                available: 100.0,
-                held: 0.0,
-                total: 100.0,
+                available: amount("100.0"),
+                held: amount("0.0"),
+                total: amount("100.0"),
                 locked: false,
             }]
         );
@@ -541,7 +1627,8 @@ mod tests {
                 transaction_type: TransactionType::Deposit,
                 client: 1,
                 transaction_id: 1,
-                amount: Some(100.0),
+                amount: Some(amount("100.0")),
+                timestamp: None,
             })
             .unwrap();
         let err = processor
@@ -550,6 +1637,7 @@ mod tests {
                 client: 1,
                 transaction_id: 1,
                 amount: None,
+                timestamp: None,
             })
             .unwrap_err();
         assert_eq!(err, TransactionProcessError::MissingAmountValue);
@@ -557,9 +1645,9 @@ mod tests {
             processor.summary(),
             vec![ClientSummary {
                 client: 1,
-                available: 100.0,
-                held: 0.0,
-                total: 100.0,
+                available: amount("100.0"),
+                held: amount("0.0"),
+                total: amount("100.0"),
                 locked: false,
             }]
         );
@@ -573,7 +1661,8 @@ mod tests {
                 transaction_type: TransactionType::Deposit,
                 client: 1,
                 transaction_id: 1,
-                amount: Some(10.0),
+                amount: Some(amount("10.0")),
+                timestamp: None,
             })
             .unwrap();
         processor
@@ -581,7 +1670,8 @@ mod tests {
                 transaction_type: TransactionType::Withdrawal,
                 client: 1,
                 transaction_id: 2,
-                amount: Some(5.0),
+                amount: Some(amount("5.0")),
+                timestamp: None,
             })
             .unwrap();
         let err = processor
@@ -589,7 +1679,8 @@ mod tests {
                 transaction_type: TransactionType::Withdrawal,
                 client: 1,
                 transaction_id: 2,
-                amount: Some(5.0),
+                amount: Some(amount("5.0")),
+                timestamp: None,
             })
             .unwrap_err();
         assert_eq!(err, TransactionProcessError::TransactionAlreadyProcessed);
@@ -597,9 +1688,9 @@ mod tests {
             processor.summary(),
             vec![ClientSummary {
                 client: 1,
-                available: 5.0,
-                held: 0.0,
-                total: 5.0,
+                available: amount("5.0"),
+                held: amount("0.0"),
+                total: amount("5.0"),
                 locked: false,
             }]
         );
@@ -614,6 +1705,7 @@ mod tests {
                 client: 1,
                 transaction_id: 2,
                 amount: None,
+                timestamp: None,
             })
             .unwrap_err();
         assert_eq!(err, TransactionProcessError::TransactionNotFound);
@@ -622,9 +1714,9 @@ mod tests {
             processor.summary(),
             vec![ClientSummary {
                 client: 1,
-                available: 0.0,
-                held: 0.0,
-                total: 0.0,
+                available: amount("0.0"),
+                held: amount("0.0"),
+                total: amount("0.0"),
                 locked: false,
             }]
         );
@@ -638,7 +1730,8 @@ mod tests {
                 transaction_type: TransactionType::Deposit,
                 client: 1,
                 transaction_id: 1,
-                amount: Some(100.0),
+                amount: Some(amount("100.0")),
+                timestamp: None,
             })
             .unwrap();
 
@@ -647,7 +1740,8 @@ mod tests {
                 transaction_type: TransactionType::Withdrawal,
                 client: 1,
                 transaction_id: 2,
-                amount: Some(20.0),
+                amount: Some(amount("20.0")),
+                timestamp: None,
             })
             .unwrap();
 
@@ -657,6 +1751,7 @@ mod tests {
                 client: 1,
                 transaction_id: 2,
                 amount: None,
+                timestamp: None,
             })
             .unwrap_err();
         assert_eq!(err, TransactionProcessError::CannotDisputeWithdrawal);
@@ -664,9 +1759,9 @@ mod tests {
             processor.summary(),
             vec![ClientSummary {
                 client: 1,
-                available: 80.0,
-                held: 0.0,
-                total: 80.0,
+                available: amount("80.0"),
+                held: amount("0.0"),
+                total: amount("80.0"),
                 locked: false,
             }]
         );
@@ -680,7 +1775,8 @@ mod tests {
                 transaction_type: TransactionType::Deposit,
                 client: 1,
                 transaction_id: 1,
-                amount: Some(100.0),
+                amount: Some(amount("100.0")),
+                timestamp: None,
             })
             .unwrap();
 
@@ -690,6 +1786,7 @@ mod tests {
                 client: 1,
                 transaction_id: 1,
                 amount: None,
+                timestamp: None,
             })
             .unwrap();
 
@@ -699,6 +1796,7 @@ mod tests {
                 client: 1,
                 transaction_id: 1,
                 amount: None,
+                timestamp: None,
             })
             .unwrap_err();
         assert_eq!(err, TransactionProcessError::TransactionAlreadyUnderDispute);
@@ -706,9 +1804,9 @@ mod tests {
             processor.summary(),
             vec![ClientSummary {
                 client: 1,
-                available: 0.0,
-                held: 100.0,
-                total: 100.0,
+                available: amount("0.0"),
+                held: amount("100.0"),
+                total: amount("100.0"),
                 locked: false,
             }]
         );
@@ -721,7 +1819,8 @@ mod tests {
                 transaction_type: TransactionType::Deposit,
                 client: 1,
                 transaction_id: 1,
-                amount: Some(100.0),
+                amount: Some(amount("100.0")),
+                timestamp: None,
             })
             .unwrap();
         processor
@@ -729,7 +1828,8 @@ mod tests {
                 transaction_type: TransactionType::Deposit,
                 client: 1,
                 transaction_id: 2,
-                amount: Some(30.0),
+                amount: Some(amount("30.0")),
+                timestamp: None,
             })
             .unwrap();
         processor
@@ -738,15 +1838,16 @@ mod tests {
                 client: 1,
                 transaction_id: 2,
                 amount: None,
+                timestamp: None,
             })
             .unwrap();
         assert_eq!(
             processor.summary(),
             vec![ClientSummary {
                 client: 1,
-                available: 100.0,
-                held: 30.0,
-                total: 130.0,
+                available: amount("100.0"),
+                held: amount("30.0"),
+                total: amount("130.0"),
                 locked: false,
             }]
         );
@@ -762,6 +1863,7 @@ mod tests {
                 client: 1,
                 transaction_id: 2,
                 amount: None,
+                timestamp: None,
             })
             .unwrap_err();
         assert_eq!(err, TransactionProcessError::TransactionNotFound);
@@ -770,9 +1872,9 @@ mod tests {
             processor.summary(),
             vec![ClientSummary {
                 client: 1,
-                available: 0.0,
-                held: 0.0,
-                total: 0.0,
+                available: amount("0.0"),
+                held: amount("0.0"),
+                total: amount("0.0"),
                 locked: false,
             }]
         );
@@ -786,7 +1888,8 @@ mod tests {
                 transaction_type: TransactionType::Deposit,
                 client: 1,
                 transaction_id: 1,
-                amount: Some(100.0),
+                amount: Some(amount("100.0")),
+                timestamp: None,
             })
             .unwrap();
 
@@ -796,6 +1899,7 @@ mod tests {
                 client: 1,
                 transaction_id: 1,
                 amount: None,
+                timestamp: None,
             })
             .unwrap_err();
         assert_eq!(err, TransactionProcessError::TransactionNotUnderDispute);
@@ -804,9 +1908,9 @@ mod tests {
             processor.summary(),
             vec![ClientSummary {
                 client: 1,
-                available: 100.0,
-                held: 0.0,
-                total: 100.0,
+                available: amount("100.0"),
+                held: amount("0.0"),
+                total: amount("100.0"),
                 locked: false,
             }]
         );
@@ -821,7 +1925,8 @@ mod tests {
                 transaction_type: TransactionType::Deposit,
                 client: 1,
                 transaction_id: 1,
-                amount: Some(100.0),
+                amount: Some(amount("100.0")),
+                timestamp: None,
             })
             .unwrap();
         processor
@@ -829,7 +1934,8 @@ mod tests {
                 transaction_type: TransactionType::Deposit,
                 client: 1,
                 transaction_id: 2,
-                amount: Some(30.0),
+                amount: Some(amount("30.0")),
+                timestamp: None,
             })
             .unwrap();
 
@@ -839,6 +1945,7 @@ mod tests {
                 client: 1,
                 transaction_id: 1,
                 amount: None,
+                timestamp: None,
             })
             .unwrap();
 
@@ -848,6 +1955,7 @@ mod tests {
                 client: 1,
                 transaction_id: 2,
                 amount: None,
+                timestamp: None,
             })
             .unwrap();
 
@@ -857,6 +1965,7 @@ mod tests {
                 client: 1,
                 transaction_id: 2,
                 amount: None,
+                timestamp: None,
             })
             .unwrap();
 
@@ -864,9 +1973,9 @@ mod tests {
             processor.summary(),
             vec![ClientSummary {
                 client: 1,
-                available: 30.0,
-                held: 100.0,
-                total: 130.0,
+                available: amount("30.0"),
+                held: amount("100.0"),
+                total: amount("130.0"),
                 locked: false,
             }]
         );
@@ -881,7 +1990,8 @@ mod tests {
                 transaction_type: TransactionType::Deposit,
                 client: 1,
                 transaction_id: 1,
-                amount: Some(100.0),
+                amount: Some(amount("100.0")),
+                timestamp: None,
             })
             .unwrap();
         processor
@@ -889,7 +1999,8 @@ mod tests {
                 transaction_type: TransactionType::Deposit,
                 client: 1,
                 transaction_id: 2,
-                amount: Some(30.0),
+                amount: Some(amount("30.0")),
+                timestamp: None,
             })
             .unwrap();
 
@@ -899,6 +2010,7 @@ mod tests {
                 client: 1,
                 transaction_id: 1,
                 amount: None,
+                timestamp: None,
             })
             .unwrap();
 
@@ -908,6 +2020,7 @@ mod tests {
                 client: 1,
                 transaction_id: 2,
                 amount: None,
+                timestamp: None,
             })
             .unwrap();
 
@@ -917,6 +2030,7 @@ mod tests {
                 client: 1,
                 transaction_id: 2,
                 amount: None,
+                timestamp: None,
             })
             .unwrap();
 
@@ -924,9 +2038,9 @@ mod tests {
             processor.summary(),
             vec![ClientSummary {
                 client: 1,
-                available: 0.0,
-                held: 100.0,
-                total: 100.0,
+                available: amount("0.0"),
+                held: amount("100.0"),
+                total: amount("100.0"),
                 locked: true,
             }]
         );
@@ -942,7 +2056,8 @@ mod tests {
                 transaction_type: TransactionType::Deposit,
                 client: 1,
                 transaction_id: 1,
-                amount: Some(100.0),
+                amount: Some(amount("100.0")),
+                timestamp: None,
             })
             .unwrap();
 
@@ -952,6 +2067,7 @@ mod tests {
                 client: 1,
                 transaction_id: 1,
                 amount: None,
+                timestamp: None,
             })
             .unwrap();
 
@@ -961,6 +2077,7 @@ mod tests {
                 client: 1,
                 transaction_id: 1,
                 amount: None,
+                timestamp: None,
             })
             .unwrap();
 
@@ -969,7 +2086,8 @@ mod tests {
                 transaction_type: TransactionType::Deposit,
                 client: 1,
                 transaction_id: 5,
-                amount: Some(100.0),
+                amount: Some(amount("100.0")),
+                timestamp: None,
             })
             .unwrap_err();
         assert_eq!(err, TransactionProcessError::AccountLocked);
@@ -979,7 +2097,8 @@ mod tests {
                 transaction_type: TransactionType::Withdrawal,
                 client: 1,
                 transaction_id: 3,
-                amount: Some(100.0),
+                amount: Some(amount("100.0")),
+                timestamp: None,
             })
             .unwrap_err();
         assert_eq!(err, TransactionProcessError::AccountLocked);
@@ -990,6 +2109,7 @@ mod tests {
                 client: 1,
                 transaction_id: 1,
                 amount: None,
+                timestamp: None,
             })
             .unwrap_err();
         assert_eq!(err, TransactionProcessError::AccountLocked);
@@ -998,9 +2118,9 @@ mod tests {
             processor.summary(),
             vec![ClientSummary {
                 client: 1,
-                available: 0.0,
-                held: 0.0,
-                total: 0.0,
+                available: amount("0.0"),
+                held: amount("0.0"),
+                total: amount("0.0"),
                 locked: true,
             }]
         );
@@ -1016,6 +2136,7 @@ mod tests {
                 client: 1,
                 transaction_id: 2,
                 amount: None,
+                timestamp: None,
             })
             .unwrap_err();
         assert_eq!(err, TransactionProcessError::TransactionNotFound);
@@ -1024,9 +2145,9 @@ mod tests {
             processor.summary(),
             vec![ClientSummary {
                 client: 1,
-                available: 0.0,
-                held: 0.0,
-                total: 0.0,
+                available: amount("0.0"),
+                held: amount("0.0"),
+                total: amount("0.0"),
                 locked: false,
             }]
         );
@@ -1040,7 +2161,8 @@ mod tests {
                 transaction_type: TransactionType::Deposit,
                 client: 1,
                 transaction_id: 1,
-                amount: Some(100.0),
+                amount: Some(amount("100.0")),
+                timestamp: None,
             })
             .unwrap();
 
@@ -1050,6 +2172,7 @@ mod tests {
                 client: 1,
                 transaction_id: 1,
                 amount: None,
+                timestamp: None,
             })
             .unwrap_err();
         assert_eq!(err, TransactionProcessError::TransactionNotUnderDispute);
@@ -1058,11 +2181,2541 @@ mod tests {
             processor.summary(),
             vec![ClientSummary {
                 client: 1,
-                available: 100.0,
-                held: 0.0,
-                total: 100.0,
+                available: amount("100.0"),
+                held: amount("0.0"),
+                total: amount("100.0"),
+                locked: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn transaction_status_should_follow_the_dispute_flow() {
+        let mut processor = TransactionsProcessor::default();
+        assert_eq!(processor.transaction_status(1, 1), None);
+
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Deposit,
+                client: 1,
+                transaction_id: 1,
+                amount: Some(amount("100.0")),
+                timestamp: None,
+            })
+            .unwrap();
+        assert_eq!(
+            processor.transaction_status(1, 1),
+            Some(TxStatusView::Processed {
+                amount: amount("100.0")
+            })
+        );
+        // Transaction ids are tracked per client
+        assert_eq!(processor.transaction_status(2, 1), None);
+
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Dispute,
+                client: 1,
+                transaction_id: 1,
+                amount: None,
+                timestamp: None,
+            })
+            .unwrap();
+        assert_eq!(
+            processor.transaction_status(1, 1),
+            Some(TxStatusView::UnderDispute {
+                amount: amount("100.0")
+            })
+        );
+
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Chargeback,
+                client: 1,
+                transaction_id: 1,
+                amount: None,
+                timestamp: None,
+            })
+            .unwrap();
+        assert_eq!(
+            processor.transaction_status(1, 1),
+            Some(TxStatusView::ChargeBack {
+                amount: amount("100.0")
+            })
+        );
+    }
+
+    #[test]
+    fn builder_should_configure_precision_and_withdrawal_disputes() {
+        let mut processor = TransactionsProcessor::builder()
+            .decimal_places(2)
+            .allow_withdrawal_disputes(true)
+            .build();
+
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Deposit,
+                client: 1,
+                transaction_id: 1,
+                amount: Some(amount("10.126")),
+                timestamp: None,
+            })
+            .unwrap();
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Withdrawal,
+                client: 1,
+                transaction_id: 2,
+                amount: Some(amount("5.0")),
+                timestamp: None,
+            })
+            .unwrap();
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Dispute,
+                client: 1,
+                transaction_id: 2,
+                amount: None,
+                timestamp: None,
+            })
+            .unwrap();
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Chargeback,
+                client: 1,
+                transaction_id: 2,
+                amount: None,
+                timestamp: None,
+            })
+            .unwrap();
+
+        // The charged back withdrawal is returned to the client
+        assert_summary_approx_eq(
+            processor.summary(),
+            vec![ClientSummary {
+                client: 1,
+                available: amount("10.13"),
+                held: amount("0.0"),
+                total: amount("10.13"),
+                locked: true,
+            }],
+        );
+    }
+
+    #[test]
+    fn only_missing_transaction_and_missing_dispute_should_be_ordering_dependent() {
+        assert!(TransactionProcessError::TransactionNotFound.is_ordering_dependent());
+        assert!(TransactionProcessError::TransactionNotUnderDispute.is_ordering_dependent());
+
+        assert!(!TransactionProcessError::NotEnoughFoundsAvailable.is_ordering_dependent());
+        assert!(!TransactionProcessError::MissingAmountValue.is_ordering_dependent());
+        assert!(!TransactionProcessError::NonPositiveAmountInTransaction.is_ordering_dependent());
+        assert!(!TransactionProcessError::AmountRoundsToZero.is_ordering_dependent());
+        assert!(!TransactionProcessError::TransactionAlreadyUnderDispute.is_ordering_dependent());
+        assert!(!TransactionProcessError::CannotDisputeWithdrawal.is_ordering_dependent());
+        assert!(!TransactionProcessError::AccountLocked.is_ordering_dependent());
+        assert!(!TransactionProcessError::TransactionAlreadyProcessed.is_ordering_dependent());
+        assert!(!TransactionProcessError::ClientIdOutOfRange.is_ordering_dependent());
+        assert!(!TransactionProcessError::TransactionIdOutOfRange.is_ordering_dependent());
+        assert!(!TransactionProcessError::CannotDisputeChargedBack.is_ordering_dependent());
+        assert!(!TransactionProcessError::WouldOverdraw.is_ordering_dependent());
+        assert!(!TransactionProcessError::AmountOutOfRange.is_ordering_dependent());
+        assert!(!TransactionProcessError::DisputeAmountMismatch.is_ordering_dependent());
+        assert!(!TransactionProcessError::TransactionIdRetired.is_ordering_dependent());
+        assert!(!TransactionProcessError::AmountExceedsLimit.is_ordering_dependent());
+        assert!(!TransactionProcessError::FundsHeldUnderDispute {
+            held: amount("1.0")
+        }
+        .is_ordering_dependent());
+        assert!(!TransactionProcessError::DisputeReopenLimitReached.is_ordering_dependent());
+        assert!(!TransactionProcessError::OpeningBalanceNotAllowed.is_ordering_dependent());
+        assert!(!TransactionProcessError::CannotDisputeSettled.is_ordering_dependent());
+        assert!(!TransactionProcessError::InvariantViolation.is_ordering_dependent());
+    }
+
+    fn processor_with_disputed_withdrawal() -> TransactionsProcessor {
+        let mut processor = TransactionsProcessor::builder()
+            .allow_withdrawal_disputes(true)
+            .build();
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Deposit,
+                client: 1,
+                transaction_id: 1,
+                amount: Some(amount("100.0")),
+                timestamp: None,
+            })
+            .unwrap();
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Withdrawal,
+                client: 1,
+                transaction_id: 2,
+                amount: Some(amount("30.0")),
+                timestamp: None,
+            })
+            .unwrap();
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Dispute,
+                client: 1,
+                transaction_id: 2,
+                amount: None,
+                timestamp: None,
+            })
+            .unwrap();
+        processor
+    }
+
+    #[test]
+    fn disputed_withdrawal_should_be_held_as_positive_amount_and_stay_deducted_from_available() {
+        let processor = processor_with_disputed_withdrawal();
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary {
+                client: 1,
+                available: amount("70.0"),
+                held: amount("30.0"),
+                total: amount("100.0"),
+                locked: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn resolved_withdrawal_dispute_should_release_held_amount() {
+        let mut processor = processor_with_disputed_withdrawal();
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Resolve,
+                client: 1,
+                transaction_id: 2,
+                amount: None,
+                timestamp: None,
+            })
+            .unwrap();
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary {
+                client: 1,
+                available: amount("70.0"),
+                held: amount("0.0"),
+                total: amount("70.0"),
+                locked: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn charged_back_withdrawal_should_return_founds_to_available_and_lock_account() {
+        let mut processor = processor_with_disputed_withdrawal();
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Chargeback,
+                client: 1,
+                transaction_id: 2,
+                amount: None,
+                timestamp: None,
+            })
+            .unwrap();
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary {
+                client: 1,
+                available: amount("100.0"),
+                held: amount("0.0"),
+                total: amount("100.0"),
+                locked: true,
+            }]
+        );
+    }
+
+    #[test]
+    fn client_id_above_configured_maximum_should_fail() {
+        let mut processor = TransactionsProcessor::builder()
+            .max_client_id(u16::MAX as ClientId)
+            .build();
+
+        let err = processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Deposit,
+                client: u16::MAX as ClientId + 1,
+                transaction_id: 1,
+                amount: Some(amount("10.0")),
+                timestamp: None,
+            })
+            .unwrap_err();
+        assert_eq!(err, TransactionProcessError::ClientIdOutOfRange);
+        assert_eq!(processor.summary(), vec![]);
+
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Deposit,
+                client: u16::MAX as ClientId,
+                transaction_id: 1,
+                amount: Some(amount("10.0")),
+                timestamp: None,
+            })
+            .unwrap();
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary {
+                client: u16::MAX as ClientId,
+                available: amount("10.0"),
+                held: amount("0.0"),
+                total: amount("10.0"),
                 locked: false,
             }]
         );
     }
+
+    #[test]
+    fn transaction_id_above_configured_maximum_should_fail() {
+        let mut processor = TransactionsProcessor::builder()
+            .max_transaction_id(u32::MAX as TransactionId)
+            .build();
+
+        let err = processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Deposit,
+                client: 1,
+                transaction_id: u32::MAX as TransactionId + 1,
+                amount: Some(amount("10.0")),
+                timestamp: None,
+            })
+            .unwrap_err();
+        assert_eq!(err, TransactionProcessError::TransactionIdOutOfRange);
+        assert_eq!(
+            processor.transaction_status(1, u32::MAX as TransactionId + 1),
+            None
+        );
+
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Deposit,
+                client: 1,
+                transaction_id: u32::MAX as TransactionId,
+                amount: Some(amount("10.0")),
+                timestamp: None,
+            })
+            .unwrap();
+        assert_eq!(
+            processor.transaction_status(1, u32::MAX as TransactionId),
+            Some(TxStatusView::Processed {
+                amount: amount("10.0")
+            })
+        );
+    }
+
+    #[test]
+    fn dispute_of_charged_back_transaction_on_unlocked_account_should_fail() {
+        let mut processor = TransactionsProcessor::default();
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Deposit,
+                client: 1,
+                transaction_id: 1,
+                amount: Some(amount("100.0")),
+                timestamp: None,
+            })
+            .unwrap();
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Dispute,
+                client: 1,
+                transaction_id: 1,
+                amount: None,
+                timestamp: None,
+            })
+            .unwrap();
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Chargeback,
+                client: 1,
+                transaction_id: 1,
+                amount: None,
+                timestamp: None,
+            })
+            .unwrap();
+
+        processor.unlock(1);
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary {
+                client: 1,
+                available: amount("0.0"),
+                held: amount("0.0"),
+                total: amount("0.0"),
+                locked: false,
+            }]
+        );
+
+        let err = processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Dispute,
+                client: 1,
+                transaction_id: 1,
+                amount: None,
+                timestamp: None,
+            })
+            .unwrap_err();
+        assert_eq!(err, TransactionProcessError::CannotDisputeChargedBack);
+    }
+
+    #[test]
+    fn retried_deposit_of_charged_back_transaction_id_should_fail_when_idempotent() {
+        let mut processor = TransactionsProcessor::builder()
+            .idempotent_deposits(true)
+            .build();
+        let deposit = Transaction {
+            transaction_type: TransactionType::Deposit,
+            client: 1,
+            transaction_id: 1,
+            amount: Some(amount("100.0")),
+            timestamp: None,
+        };
+        processor.process(&deposit).unwrap();
+        for transaction_type in [TransactionType::Dispute, TransactionType::Chargeback] {
+            processor
+                .process(&Transaction {
+                    transaction_type,
+                    amount: None,
+                    ..deposit.clone()
+                })
+                .unwrap();
+        }
+        processor.unlock(1);
+
+        assert_eq!(
+            processor.process(&deposit),
+            Err(TransactionProcessError::TransactionIdRetired)
+        );
+    }
+
+    #[test]
+    fn reusing_charged_back_transaction_id_after_unlock_should_fail() {
+        let mut processor = TransactionsProcessor::default();
+        for (transaction_type, amount) in [
+            (TransactionType::Deposit, Some(amount("100.0"))),
+            (TransactionType::Dispute, None),
+            (TransactionType::Chargeback, None),
+        ] {
+            processor
+                .process(&Transaction {
+                    transaction_type,
+                    client: 1,
+                    transaction_id: 1,
+                    amount,
+                    timestamp: None,
+                })
+                .unwrap();
+        }
+        processor.unlock(1);
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Deposit,
+                client: 1,
+                transaction_id: 2,
+                amount: Some(amount("100.0")),
+                timestamp: None,
+            })
+            .unwrap();
+
+        for transaction_type in [TransactionType::Deposit, TransactionType::Withdrawal] {
+            let err = processor
+                .process(&Transaction {
+                    transaction_type,
+                    client: 1,
+                    transaction_id: 1,
+                    amount: Some(amount("100.0")),
+                    timestamp: None,
+                })
+                .unwrap_err();
+            assert_eq!(err, TransactionProcessError::TransactionIdRetired);
+        }
+        assert_eq!(
+            processor.transaction_status(1, 1),
+            Some(TxStatusView::ChargeBack {
+                amount: amount("100.0")
+            })
+        );
+    }
+
+    #[test]
+    fn half_up_rounding_should_round_ties_away_from_zero() {
+        let config = ProcessorConfig::default();
+        let to_amount = |v| config.amount_to_internal::<AmountType>(v).unwrap();
+        assert_eq!(to_amount(amount("0.00005")), 1);
+        assert_eq!(to_amount(amount("0.00025")), 3);
+        assert_eq!(to_amount(amount("0.00035")), 4);
+        assert_eq!(to_amount(amount("-0.00025")), -3);
+        assert_eq!(to_amount(amount("0.00004")), 0);
+    }
+
+    #[test]
+    fn half_even_rounding_should_round_ties_to_even() {
+        let config = ProcessorConfig {
+            rounding_mode: RoundingMode::HalfEven,
+            ..Default::default()
+        };
+        let to_amount = |v| config.amount_to_internal::<AmountType>(v).unwrap();
+        assert_eq!(to_amount(amount("0.00005")), 0);
+        assert_eq!(to_amount(amount("0.00025")), 2);
+        assert_eq!(to_amount(amount("0.00035")), 4);
+        assert_eq!(to_amount(amount("-0.00025")), -2);
+        assert_eq!(to_amount(amount("0.00006")), 1);
+    }
+
+    #[test]
+    fn half_even_rounding_should_make_tie_deposit_round_to_zero() {
+        let mut processor = TransactionsProcessor::builder()
+            .rounding_mode(RoundingMode::HalfEven)
+            .build();
+        let err = processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Deposit,
+                client: 1,
+                transaction_id: 1,
+                amount: Some(amount("0.00005")),
+                timestamp: None,
+            })
+            .unwrap_err();
+        assert_eq!(err, TransactionProcessError::AmountRoundsToZero);
+    }
+
+    #[test]
+    fn processor_should_not_be_empty_after_first_transaction() {
+        let mut processor = TransactionsProcessor::default();
+        assert!(processor.is_empty());
+        assert_eq!(processor.client_count(), 0);
+
+        for (client, transaction_id) in [(1, 1), (2, 2), (1, 3)] {
+            processor
+                .process(&Transaction {
+                    transaction_type: TransactionType::Deposit,
+                    client,
+                    transaction_id,
+                    amount: Some(amount("10.0")),
+                    timestamp: None,
+                })
+                .unwrap();
+            assert!(!processor.is_empty());
+        }
+        assert_eq!(processor.client_count(), 2);
+    }
+
+    #[test]
+    fn active_account_count_should_skip_empty_and_locked_accounts() {
+        let mut processor = TransactionsProcessor::default();
+        assert_eq!(processor.active_account_count(), 0);
+
+        let transactions = [
+            // Client 1 has non zero balance
+            (TransactionType::Deposit, 1, 1, Some(amount("10.0"))),
+            // Client 2 withdraws everything
+            (TransactionType::Deposit, 2, 2, Some(amount("10.0"))),
+            (TransactionType::Withdrawal, 2, 3, Some(amount("10.0"))),
+            // Client 3 has only held founds
+            (TransactionType::Deposit, 3, 4, Some(amount("10.0"))),
+            (TransactionType::Dispute, 3, 4, None),
+            // Client 4 is locked with non zero balance
+            (TransactionType::Deposit, 4, 5, Some(amount("10.0"))),
+            (TransactionType::Deposit, 4, 6, Some(amount("10.0"))),
+            (TransactionType::Dispute, 4, 6, None),
+            (TransactionType::Chargeback, 4, 6, None),
+        ];
+        for (transaction_type, client, transaction_id, amount) in transactions {
+            processor
+                .process(&Transaction {
+                    transaction_type,
+                    client,
+                    transaction_id,
+                    amount,
+                    timestamp: None,
+                })
+                .unwrap();
+        }
+
+        assert_eq!(processor.active_account_count(), 2);
+    }
+
+    #[test]
+    fn apply_and_summarize_should_return_updated_summary() {
+        let mut processor = TransactionsProcessor::default();
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Deposit,
+                client: 1,
+                transaction_id: 1,
+                amount: Some(amount("100.0")),
+                timestamp: None,
+            })
+            .unwrap();
+
+        let summary = processor
+            .apply_and_summarize(&Transaction {
+                transaction_type: TransactionType::Withdrawal,
+                client: 1,
+                transaction_id: 2,
+                amount: Some(amount("30.0")),
+                timestamp: None,
+            })
+            .unwrap();
+
+        let expected = vec![ClientSummary {
+            client: 1,
+            available: amount("70.0"),
+            held: amount("0.0"),
+            total: amount("70.0"),
+            locked: false,
+        }];
+        assert_eq!(summary, expected);
+
+        // Rejected withdrawal of a new client does not add its account
+        assert_eq!(
+            processor.apply_and_summarize(&Transaction {
+                transaction_type: TransactionType::Withdrawal,
+                client: 2,
+                transaction_id: 3,
+                amount: Some(amount("30.0")),
+                timestamp: None,
+            }),
+            Err(TransactionProcessError::NotEnoughFoundsAvailable)
+        );
+        assert_eq!(processor.summary(), expected);
+    }
+
+    #[test]
+    fn process_and_get_should_return_updated_summary_of_the_client() {
+        let mut processor = TransactionsProcessor::default();
+        for (client, transaction_id, amount) in [(1, 1, amount("100.0")), (2, 2, amount("50.0"))] {
+            processor
+                .process(&Transaction {
+                    transaction_type: TransactionType::Deposit,
+                    client,
+                    transaction_id,
+                    amount: Some(amount),
+                    timestamp: None,
+                })
+                .unwrap();
+        }
+
+        let summary = processor
+            .process_and_get(&Transaction {
+                transaction_type: TransactionType::Withdrawal,
+                client: 2,
+                transaction_id: 3,
+                amount: Some(amount("20.0")),
+                timestamp: None,
+            })
+            .unwrap();
+
+        assert_eq!(
+            summary,
+            ClientSummary::new(2, amount("30.0"), amount("0.0"), false)
+        );
+        assert_eq!(processor.summary()[1], summary);
+
+        // Rejected transaction of a new client does not add its account
+        assert_eq!(
+            processor.process_and_get(&Transaction {
+                transaction_type: TransactionType::Withdrawal,
+                client: 3,
+                transaction_id: 4,
+                amount: Some(amount("20.0")),
+                timestamp: None,
+            }),
+            Err(TransactionProcessError::NotEnoughFoundsAvailable)
+        );
+        assert_eq!(processor.summary().len(), 2);
+    }
+
+    fn deposit_three_transactions() -> TransactionsProcessor {
+        let mut processor = TransactionsProcessor::default();
+        for transaction_id in 1..=3 {
+            processor
+                .process(&Transaction {
+                    transaction_type: TransactionType::Deposit,
+                    client: 1,
+                    transaction_id,
+                    amount: Some(amount::from_scaled(10 * transaction_id, 0)),
+                    timestamp: None,
+                })
+                .unwrap();
+        }
+        processor
+    }
+
+    #[test]
+    fn dispute_batch_should_dispute_all_transactions() {
+        let mut processor = deposit_three_transactions();
+
+        assert_eq!(processor.dispute_batch(1, &[1, 3]), Ok(()));
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary::new(1, amount("20.0"), amount("40.0"), false)]
+        );
+    }
+
+    #[test]
+    fn dispute_batch_with_invalid_transaction_should_dispute_none() {
+        let mut processor = deposit_three_transactions();
+
+        assert_eq!(
+            processor.dispute_batch(1, &[1, 9, 3]),
+            Err((9, TransactionProcessError::TransactionNotFound))
+        );
+        assert_eq!(
+            processor.dispute_batch(1, &[2, 2]),
+            Err((2, TransactionProcessError::TransactionAlreadyUnderDispute))
+        );
+        for transaction_id in 1..=3 {
+            assert_eq!(
+                processor.transaction_status(1, transaction_id),
+                Some(TxStatusView::Processed {
+                    amount: amount::from_scaled(10 * transaction_id, 0)
+                })
+            );
+        }
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary::new(1, amount("60.0"), amount("0.0"), false)]
+        );
+        assert_eq!(
+            processor.dispute_batch(2, &[1]),
+            Err((1, TransactionProcessError::TransactionNotFound))
+        );
+        assert_eq!(processor.client_count(), 1);
+    }
+
+    #[test]
+    fn simulate_chargeback_should_not_change_processor() {
+        let mut processor = TransactionsProcessor::default();
+        let transactions = [
+            (TransactionType::Deposit, 1, Some(amount("100.0"))),
+            (TransactionType::Deposit, 2, Some(amount("20.0"))),
+            (TransactionType::Dispute, 2, None),
+        ];
+        for (transaction_type, transaction_id, amount) in transactions {
+            processor
+                .process(&Transaction {
+                    transaction_type,
+                    client: 1,
+                    transaction_id,
+                    amount,
+                    timestamp: None,
+                })
+                .unwrap();
+        }
+        let before = processor.summary();
+        let chargeback = Transaction {
+            transaction_type: TransactionType::Chargeback,
+            client: 1,
+            transaction_id: 2,
+            amount: None,
+            timestamp: None,
+        };
+
+        assert_eq!(
+            processor.simulate(&chargeback),
+            Ok(ClientSummary {
+                client: 1,
+                available: amount("100.0"),
+                held: amount("0.0"),
+                total: amount("100.0"),
+                locked: true,
+            })
+        );
+        assert_eq!(processor.summary(), before);
+        assert_eq!(
+            processor.transaction_status(1, 2),
+            Some(TxStatusView::UnderDispute {
+                amount: amount("20.0")
+            })
+        );
+        // The simulated chargeback can be still applied
+        processor.process(&chargeback).unwrap();
+        assert_eq!(
+            processor.simulate(&chargeback),
+            Err(TransactionProcessError::AccountLocked)
+        );
+    }
+
+    #[test]
+    fn capacity_should_not_change_processing() {
+        let transactions = (1..=40)
+            .map(|transaction_id| Transaction {
+                transaction_type: match transaction_id % 4 {
+                    0 => TransactionType::Withdrawal,
+                    1 | 2 => TransactionType::Deposit,
+                    _ => TransactionType::Dispute,
+                },
+                client: transaction_id % 7,
+                transaction_id: match transaction_id % 4 {
+                    3 => transaction_id - 2,
+                    _ => transaction_id,
+                },
+                amount: (transaction_id % 4 != 3).then_some(amount::from_scaled(transaction_id, 0)),
+                timestamp: None,
+            })
+            .collect::<Vec<_>>();
+        let process_all = |mut processor: TransactionsProcessor| {
+            let results = transactions
+                .iter()
+                .map(|transaction| processor.process(transaction))
+                .collect::<Vec<_>>();
+            (results, processor.summary())
+        };
+
+        let expected = process_all(TransactionsProcessor::default());
+        assert_eq!(
+            process_all(TransactionsProcessor::with_capacity(0)),
+            expected
+        );
+        assert_eq!(
+            process_all(TransactionsProcessor::with_capacity(1000)),
+            expected
+        );
+        assert_eq!(
+            process_all(TransactionsProcessor::builder().capacity(3, 100).build()),
+            expected
+        );
+    }
+
+    #[test]
+    fn status_counts_should_count_records_in_each_status() {
+        let mut processor = TransactionsProcessor::default();
+        assert_eq!(processor.status_counts(1), None);
+
+        let transactions = [
+            (TransactionType::Deposit, 1, Some(amount("100.0"))),
+            (TransactionType::Deposit, 2, Some(amount("20.0"))),
+            (TransactionType::Deposit, 3, Some(amount("30.0"))),
+            (TransactionType::Dispute, 2, None),
+            (TransactionType::Dispute, 3, None),
+            (TransactionType::Chargeback, 3, None),
+        ];
+        for (transaction_type, transaction_id, amount) in transactions {
+            processor
+                .process(&Transaction {
+                    transaction_type,
+                    client: 1,
+                    transaction_id,
+                    amount,
+                    timestamp: None,
+                })
+                .unwrap();
+        }
+
+        assert_eq!(
+            processor.status_counts(1),
+            Some(StatusCounts {
+                processed: 1,
+                under_dispute: 1,
+                charged_back: 1,
+                settled: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn clamp_negative_available_should_report_zero_and_track_shortfall() {
+        let mut processor = TransactionsProcessor::builder()
+            .clamp_negative_available(true)
+            .build();
+        process_withdrawal_and_dispute_of_deposit(&mut processor);
+
+        // True available is -300 after the dispute of already withdrawn deposit
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary::new(3, amount("0.0"), amount("300.0"), false)]
+        );
+        assert_eq!(processor.shortfall(3), Some(amount("300.0")));
+        assert_eq!(processor.shortfall(1), None);
+
+        // Later deposit covers the shortfall
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Deposit,
+                client: 3,
+                transaction_id: 34,
+                amount: Some(amount("310.0")),
+                timestamp: None,
+            })
+            .unwrap();
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary::new(
+                3,
+                amount("10.0"),
+                amount("300.0"),
+                false
+            )]
+        );
+        assert_eq!(processor.shortfall(3), Some(amount("0.0")));
+    }
+
+    #[test]
+    fn negative_available_should_be_reported_by_default() {
+        let mut processor = TransactionsProcessor::default();
+        process_withdrawal_and_dispute_of_deposit(&mut processor);
+
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary::new(
+                3,
+                amount("-300.0"),
+                amount("300.0"),
+                false
+            )]
+        );
+        assert_eq!(processor.shortfall(3), Some(amount("300.0")));
+    }
+
+    fn opening_balance(transaction_id: TransactionId, balance: Amount) -> Transaction {
+        Transaction {
+            transaction_type: TransactionType::OpeningBalance,
+            client: 1,
+            transaction_id,
+            amount: Some(balance),
+            timestamp: None,
+        }
+    }
+
+    #[test]
+    fn opening_balance_should_set_available_of_new_client() {
+        let mut processor = TransactionsProcessor::default();
+        processor
+            .process(&opening_balance(1, amount("250.0")))
+            .unwrap();
+
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary::new(1, amount("250.0"), amount("0.0"), false)]
+        );
+        // Opening balance is settled and cannot be disputed
+        assert_eq!(
+            processor.process(&Transaction {
+                transaction_type: TransactionType::Dispute,
+                client: 1,
+                transaction_id: 1,
+                amount: None,
+                timestamp: None,
+            }),
+            Err(TransactionProcessError::CannotDisputeSettled)
+        );
+        assert_eq!(
+            processor.process(&opening_balance(2, amount("100.0"))),
+            Err(TransactionProcessError::OpeningBalanceNotAllowed)
+        );
+    }
+
+    #[test]
+    fn opening_balance_should_be_kept_in_history() {
+        let mut processor = TransactionsProcessor::default();
+        processor
+            .process(&opening_balance(1, amount("250.0")))
+            .unwrap();
+
+        assert_eq!(
+            processor.transaction_status(1, 1),
+            Some(TxStatusView::Settled {
+                amount: amount("250.0")
+            })
+        );
+        assert_eq!(
+            processor.client_history(1),
+            vec![TransactionRecordView {
+                transaction_id: 1,
+                amount: amount("250.0"),
+                status: TransactionStatus::Settled,
+            }]
+        );
+        assert_eq!(
+            processor.status_counts(1),
+            Some(StatusCounts {
+                settled: 1,
+                ..StatusCounts::default()
+            })
+        );
+        // Id of the opening balance is reserved
+        assert_eq!(
+            processor.process(&Transaction {
+                transaction_type: TransactionType::Deposit,
+                client: 1,
+                transaction_id: 1,
+                amount: Some(amount("10.0")),
+                timestamp: None,
+            }),
+            Err(TransactionProcessError::TransactionAlreadyProcessed)
+        );
+    }
+
+    #[test]
+    fn opening_balance_after_client_activity_should_fail() {
+        let mut processor = TransactionsProcessor::default();
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Deposit,
+                client: 1,
+                transaction_id: 1,
+                amount: Some(amount("10.0")),
+                timestamp: None,
+            })
+            .unwrap();
+
+        assert_eq!(
+            processor.process(&opening_balance(2, amount("250.0"))),
+            Err(TransactionProcessError::OpeningBalanceNotAllowed)
+        );
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary::new(1, amount("10.0"), amount("0.0"), false)]
+        );
+    }
+
+    #[test]
+    fn withdrawable_should_exclude_held_founds() {
+        let mut processor = TransactionsProcessor::default();
+        assert_eq!(processor.withdrawable(1), None);
+
+        let transactions = [
+            (TransactionType::Deposit, 1, Some(amount("100.0"))),
+            (TransactionType::Deposit, 2, Some(amount("20.0"))),
+            (TransactionType::Dispute, 2, None),
+        ];
+        for (transaction_type, transaction_id, amount) in transactions {
+            processor
+                .process(&Transaction {
+                    transaction_type,
+                    client: 1,
+                    transaction_id,
+                    amount,
+                    timestamp: None,
+                })
+                .unwrap();
+        }
+        assert_eq!(processor.withdrawable(1), Some(amount("100.0")));
+
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Chargeback,
+                client: 1,
+                transaction_id: 2,
+                amount: None,
+                timestamp: None,
+            })
+            .unwrap();
+        assert_eq!(processor.withdrawable(1), Some(amount("0.0")));
+    }
+
+    #[test]
+    fn locked_clients_should_return_only_charged_back_clients() {
+        let mut processor = TransactionsProcessor::default();
+        assert!(processor.locked_clients().is_empty());
+
+        let transactions = [
+            (TransactionType::Deposit, 3, 1, Some(amount("10.0"))),
+            (TransactionType::Deposit, 1, 2, Some(amount("10.0"))),
+            (TransactionType::Deposit, 2, 3, Some(amount("10.0"))),
+            (TransactionType::Dispute, 1, 2, None),
+            (TransactionType::Dispute, 3, 1, None),
+            (TransactionType::Chargeback, 3, 1, None),
+            (TransactionType::Chargeback, 1, 2, None),
+        ];
+        for (transaction_type, client, transaction_id, amount) in transactions {
+            processor
+                .process(&Transaction {
+                    transaction_type,
+                    client,
+                    transaction_id,
+                    amount,
+                    timestamp: None,
+                })
+                .unwrap();
+        }
+
+        assert_eq!(processor.locked_clients(), vec![1, 3]);
+    }
+
+    /// Deposits, withdraws everything and disputes the first deposit, so its chargeback makes available negative
+    fn process_withdrawal_and_dispute_of_deposit(processor: &mut TransactionsProcessor) {
+        let transactions = [
+            (TransactionType::Deposit, 31, Some(amount("300.0"))),
+            (TransactionType::Deposit, 32, Some(amount("400.0"))),
+            (TransactionType::Withdrawal, 33, Some(amount("700.0"))),
+            (TransactionType::Dispute, 31, None),
+        ];
+        for (transaction_type, transaction_id, amount) in transactions {
+            processor
+                .process(&Transaction {
+                    transaction_type,
+                    client: 3,
+                    transaction_id,
+                    amount,
+                    timestamp: None,
+                })
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn chargeback_making_available_negative_should_succeed_by_default() {
+        let mut processor = TransactionsProcessor::default();
+        process_withdrawal_and_dispute_of_deposit(&mut processor);
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Chargeback,
+                client: 3,
+                transaction_id: 31,
+                amount: None,
+                timestamp: None,
+            })
+            .unwrap();
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary {
+                client: 3,
+                available: amount("-300.0"),
+                held: amount("0.0"),
+                total: amount("-300.0"),
+                locked: true,
+            }]
+        );
+    }
+
+    #[test]
+    fn chargeback_making_available_negative_should_fail_if_negative_balance_not_allowed() {
+        let mut processor = TransactionsProcessor::builder()
+            .allow_negative_balance(false)
+            .build();
+        process_withdrawal_and_dispute_of_deposit(&mut processor);
+        let err = processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Chargeback,
+                client: 3,
+                transaction_id: 31,
+                amount: None,
+                timestamp: None,
+            })
+            .unwrap_err();
+        assert_eq!(err, TransactionProcessError::WouldOverdraw);
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary {
+                client: 3,
+                available: amount("-300.0"),
+                held: amount("300.0"),
+                total: amount("0.0"),
+                locked: false,
+            }]
+        );
+        assert_eq!(
+            processor.transaction_status(3, 31),
+            Some(TxStatusView::UnderDispute {
+                amount: amount("300.0")
+            })
+        );
+    }
+
+    fn withdraw_after_disputed_deposit(
+        processor: &mut TransactionsProcessor,
+        withdrawal: Amount,
+    ) -> Result<(), TransactionProcessError> {
+        process_partial_dispute(processor, amount("40.0")).unwrap();
+        processor.process(&Transaction {
+            transaction_type: TransactionType::Withdrawal,
+            client: 1,
+            transaction_id: 2,
+            amount: Some(withdrawal),
+            timestamp: None,
+        })
+    }
+
+    #[test]
+    fn withdrawal_covered_by_held_founds_should_fail_with_held_amount_if_explained() {
+        let mut processor = TransactionsProcessor::builder()
+            .explain_held_founds(true)
+            .build();
+
+        assert_eq!(
+            withdraw_after_disputed_deposit(&mut processor, amount("80.0")),
+            Err(TransactionProcessError::FundsHeldUnderDispute {
+                held: amount("40.0")
+            })
+        );
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary::new(1, amount("60.0"), amount("40.0"), false)]
+        );
+    }
+
+    #[test]
+    fn withdrawal_over_available_and_held_founds_should_fail_with_not_enough_founds() {
+        let mut processor = TransactionsProcessor::builder()
+            .explain_held_founds(true)
+            .build();
+
+        assert_eq!(
+            withdraw_after_disputed_deposit(&mut processor, amount("100.5")),
+            Err(TransactionProcessError::NotEnoughFoundsAvailable)
+        );
+        assert_eq!(
+            withdraw_after_disputed_deposit(&mut TransactionsProcessor::default(), amount("80.0")),
+            Err(TransactionProcessError::NotEnoughFoundsAvailable)
+        );
+    }
+
+    /// Disputes and resolves the deposit the given number of times, returns result of the last dispute
+    fn dispute_resolved_deposit_repeatedly(
+        processor: &mut TransactionsProcessor,
+        disputes: usize,
+    ) -> Result<(), TransactionProcessError> {
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Deposit,
+                client: 1,
+                transaction_id: 1,
+                amount: Some(amount("100.0")),
+                timestamp: None,
+            })
+            .unwrap();
+        let dispute_flow = |transaction_type| Transaction {
+            transaction_type,
+            client: 1,
+            transaction_id: 1,
+            amount: None,
+            timestamp: None,
+        };
+        for _ in 1..disputes {
+            processor.process(&dispute_flow(TransactionType::Dispute))?;
+            processor.process(&dispute_flow(TransactionType::Resolve))?;
+        }
+        processor.process(&dispute_flow(TransactionType::Dispute))
+    }
+
+    #[test]
+    fn dispute_reopen_within_limit_should_succeed() {
+        let mut processor = TransactionsProcessor::builder()
+            .max_dispute_reopens(1)
+            .build();
+
+        assert_eq!(
+            dispute_resolved_deposit_repeatedly(&mut processor, 2),
+            Ok(())
+        );
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary::new(1, amount("0.0"), amount("100.0"), false)]
+        );
+    }
+
+    #[test]
+    fn dispute_reopen_over_limit_should_fail() {
+        let mut processor = TransactionsProcessor::builder()
+            .max_dispute_reopens(1)
+            .build();
+
+        assert_eq!(
+            dispute_resolved_deposit_repeatedly(&mut processor, 3),
+            Err(TransactionProcessError::DisputeReopenLimitReached)
+        );
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary::new(1, amount("100.0"), amount("0.0"), false)]
+        );
+    }
+
+    #[test]
+    fn dispute_reopens_should_be_unlimited_by_default() {
+        assert_eq!(
+            dispute_resolved_deposit_repeatedly(&mut TransactionsProcessor::default(), 10),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn every_error_should_have_unique_code() {
+        let errors = [
+            TransactionProcessError::NotEnoughFoundsAvailable,
+            TransactionProcessError::MissingAmountValue,
+            TransactionProcessError::NonPositiveAmountInTransaction,
+            TransactionProcessError::AmountRoundsToZero,
+            TransactionProcessError::TransactionNotFound,
+            TransactionProcessError::TransactionAlreadyUnderDispute,
+            TransactionProcessError::CannotDisputeWithdrawal,
+            TransactionProcessError::TransactionNotUnderDispute,
+            TransactionProcessError::AccountLocked,
+            TransactionProcessError::TransactionAlreadyProcessed,
+            TransactionProcessError::ClientIdOutOfRange,
+            TransactionProcessError::TransactionIdOutOfRange,
+            TransactionProcessError::CannotDisputeChargedBack,
+            TransactionProcessError::WouldOverdraw,
+            TransactionProcessError::AmountOutOfRange,
+            TransactionProcessError::DisputeAmountMismatch,
+            TransactionProcessError::TransactionIdRetired,
+            TransactionProcessError::AmountExceedsLimit,
+            TransactionProcessError::FundsHeldUnderDispute {
+                held: amount("1.0"),
+            },
+            TransactionProcessError::DisputeReopenLimitReached,
+            TransactionProcessError::OpeningBalanceNotAllowed,
+            TransactionProcessError::CannotDisputeSettled,
+            TransactionProcessError::InvariantViolation,
+        ];
+        let codes = errors.iter().map(|err| err.code()).unique().count();
+        assert_eq!(codes, errors.len());
+        assert_eq!(
+            TransactionProcessError::NotEnoughFoundsAvailable.code(),
+            "not-enough-funds"
+        );
+    }
+
+    fn deposit_twice<A: AmountInt>(
+        processor: &mut TransactionsProcessor<A>,
+        deposit: Amount,
+    ) -> Result<(), TransactionProcessError> {
+        processor.process(&Transaction {
+            transaction_type: TransactionType::Deposit,
+            client: 1,
+            transaction_id: 1,
+            amount: Some(deposit),
+            timestamp: None,
+        })?;
+        processor.process(&Transaction {
+            transaction_type: TransactionType::Deposit,
+            client: 1,
+            transaction_id: 2,
+            amount: Some(deposit),
+            timestamp: None,
+        })
+    }
+
+    #[test]
+    fn i128_amounts_should_allow_sums_overflowing_i64() {
+        // Each deposit fits in i64 after scaling, but their sum does not
+        let deposit = amount("500000000000000.0");
+
+        let mut processor = TransactionsProcessor::default();
+        assert_eq!(
+            deposit_twice(&mut processor, deposit),
+            Err(TransactionProcessError::AmountOutOfRange)
+        );
+
+        let mut processor: TransactionsProcessor<i128> =
+            TransactionsProcessorBuilder::default().build();
+        deposit_twice(&mut processor, deposit).unwrap();
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary {
+                client: 1,
+                available: amount("2.0") * deposit,
+                held: amount("0.0"),
+                total: amount("2.0") * deposit,
+                locked: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn i32_amounts_should_reject_amounts_out_of_range() {
+        let mut processor: TransactionsProcessor<i32> =
+            TransactionsProcessorBuilder::default().build();
+        let err = processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Deposit,
+                client: 1,
+                transaction_id: 1,
+                amount: Some(amount("1000000.0")),
+                timestamp: None,
+            })
+            .unwrap_err();
+        assert_eq!(err, TransactionProcessError::AmountOutOfRange);
+    }
+
+    #[test]
+    fn summary_sorted_by_should_apply_comparator() {
+        let mut processor = TransactionsProcessor::default();
+        for (client, amount) in [(1, amount("5.0")), (2, amount("20.0")), (3, amount("10.0"))] {
+            processor
+                .process(&Transaction {
+                    transaction_type: TransactionType::Deposit,
+                    client,
+                    transaction_id: client as TransactionId,
+                    amount: Some(amount),
+                    timestamp: None,
+                })
+                .unwrap();
+        }
+
+        let clients = processor
+            .summary_sorted_by(|a, b| b.total.partial_cmp(&a.total).unwrap())
+            .into_iter()
+            .map(|summary| summary.client)
+            .collect_vec();
+        assert_eq!(clients, vec![2, 3, 1]);
+    }
+
+    /// Deposit disputed and resolved, then partially withdrawn and disputed again
+    fn process_redispute_after_resolve_and_withdrawal(processor: &mut TransactionsProcessor) {
+        for (transaction_type, transaction_id, amount) in [
+            (TransactionType::Deposit, 1, Some(amount("10.0"))),
+            (TransactionType::Dispute, 1, None),
+            (TransactionType::Resolve, 1, None),
+            (TransactionType::Withdrawal, 2, Some(amount("8.0"))),
+            (TransactionType::Dispute, 1, None),
+        ] {
+            processor
+                .process(&Transaction {
+                    transaction_type,
+                    client: 1,
+                    transaction_id,
+                    amount,
+                    timestamp: None,
+                })
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn chargeback_of_redisputed_deposit_after_withdrawal_should_leave_negative_available() {
+        let mut processor = TransactionsProcessor::default();
+        process_redispute_after_resolve_and_withdrawal(&mut processor);
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Chargeback,
+                client: 1,
+                transaction_id: 1,
+                amount: None,
+                timestamp: None,
+            })
+            .unwrap();
+        // Defined behavior: the withdrawn founds are not restored, so the account ends up in debt and locked
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary {
+                client: 1,
+                available: amount("-8.0"),
+                held: amount("0.0"),
+                total: amount("-8.0"),
+                locked: true,
+            }]
+        );
+    }
+
+    #[test]
+    fn chargeback_of_redisputed_deposit_after_withdrawal_should_fail_if_negative_balance_not_allowed(
+    ) {
+        let mut processor = TransactionsProcessor::builder()
+            .allow_negative_balance(false)
+            .build();
+        process_redispute_after_resolve_and_withdrawal(&mut processor);
+        let err = processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Chargeback,
+                client: 1,
+                transaction_id: 1,
+                amount: None,
+                timestamp: None,
+            })
+            .unwrap_err();
+        assert_eq!(err, TransactionProcessError::WouldOverdraw);
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary {
+                client: 1,
+                available: amount("-8.0"),
+                held: amount("10.0"),
+                total: amount("2.0"),
+                locked: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn client_history_should_return_transactions_in_processing_order() {
+        let mut processor = TransactionsProcessor::default();
+        for (transaction_type, transaction_id, amount) in [
+            (TransactionType::Deposit, 30, Some(amount("100.0"))),
+            (TransactionType::Withdrawal, 10, Some(amount("40.0"))),
+            (TransactionType::Deposit, 20, Some(amount("5.0"))),
+            (TransactionType::Dispute, 20, None),
+        ] {
+            processor
+                .process(&Transaction {
+                    transaction_type,
+                    client: 1,
+                    transaction_id,
+                    amount,
+                    timestamp: None,
+                })
+                .unwrap();
+        }
+
+        assert_eq!(
+            processor.client_history(1),
+            vec![
+                TransactionRecordView {
+                    transaction_id: 30,
+                    amount: amount("100.0"),
+                    status: TransactionStatus::Processed,
+                },
+                TransactionRecordView {
+                    transaction_id: 10,
+                    amount: amount("-40.0"),
+                    status: TransactionStatus::Processed,
+                },
+                TransactionRecordView {
+                    transaction_id: 20,
+                    amount: amount("5.0"),
+                    status: TransactionStatus::UnderDispute,
+                },
+            ]
+        );
+        assert_eq!(processor.client_history(2), vec![]);
+    }
+
+    #[test]
+    fn idempotent_deposits_should_ignore_retried_deposit() {
+        let mut processor = TransactionsProcessor::builder()
+            .idempotent_deposits(true)
+            .build();
+        let deposit = Transaction {
+            transaction_type: TransactionType::Deposit,
+            client: 1,
+            transaction_id: 1,
+            amount: Some(amount("10.0")),
+            timestamp: None,
+        };
+        processor.process(&deposit).unwrap();
+        processor.process(&deposit).unwrap();
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary {
+                client: 1,
+                available: amount("10.0"),
+                held: amount("0.0"),
+                total: amount("10.0"),
+                locked: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn idempotent_deposits_should_reject_repeated_deposit_with_different_amount() {
+        let mut processor = TransactionsProcessor::builder()
+            .idempotent_deposits(true)
+            .build();
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Deposit,
+                client: 1,
+                transaction_id: 1,
+                amount: Some(amount("10.0")),
+                timestamp: None,
+            })
+            .unwrap();
+        let err = processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Deposit,
+                client: 1,
+                transaction_id: 1,
+                amount: Some(amount("11.0")),
+                timestamp: None,
+            })
+            .unwrap_err();
+        assert_eq!(err, TransactionProcessError::TransactionAlreadyProcessed);
+    }
+
+    #[test]
+    fn idempotent_deposits_should_reject_retry_of_disputed_deposit() {
+        let mut processor = TransactionsProcessor::builder()
+            .idempotent_deposits(true)
+            .build();
+        let deposit = Transaction {
+            transaction_type: TransactionType::Deposit,
+            client: 1,
+            transaction_id: 1,
+            amount: Some(amount("10.0")),
+            timestamp: None,
+        };
+        processor.process(&deposit).unwrap();
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Dispute,
+                amount: None,
+                ..deposit.clone()
+            })
+            .unwrap();
+
+        assert_eq!(
+            processor.process(&deposit),
+            Err(TransactionProcessError::TransactionAlreadyProcessed)
+        );
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary {
+                client: 1,
+                available: amount("0.0"),
+                held: amount("10.0"),
+                total: amount("10.0"),
+                locked: false,
+            }]
+        );
+    }
+
+    fn admin_transaction(transaction_type: TransactionType) -> Transaction {
+        Transaction {
+            transaction_type,
+            client: 1,
+            transaction_id: 0,
+            amount: None,
+            timestamp: None,
+        }
+    }
+
+    #[test]
+    fn frozen_account_should_reject_transactions_until_unfrozen() {
+        let mut processor = TransactionsProcessor::default();
+        let deposit = |transaction_id| Transaction {
+            transaction_type: TransactionType::Deposit,
+            client: 1,
+            transaction_id,
+            amount: Some(amount("10.0")),
+            timestamp: None,
+        };
+        processor.process(&deposit(1)).unwrap();
+        processor
+            .process(&admin_transaction(TransactionType::Freeze))
+            .unwrap();
+
+        let err = processor.process(&deposit(2)).unwrap_err();
+        assert_eq!(err, TransactionProcessError::AccountLocked);
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary {
+                client: 1,
+                available: amount("10.0"),
+                held: amount("0.0"),
+                total: amount("10.0"),
+                locked: true,
+            }]
+        );
+
+        processor
+            .process(&admin_transaction(TransactionType::Unfreeze))
+            .unwrap();
+        processor.process(&deposit(2)).unwrap();
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary {
+                client: 1,
+                available: amount("20.0"),
+                held: amount("0.0"),
+                total: amount("20.0"),
+                locked: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn unfreeze_should_not_unlock_account_locked_by_chargeback() {
+        let mut processor = TransactionsProcessor::default();
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Deposit,
+                client: 1,
+                transaction_id: 1,
+                amount: Some(amount("10.0")),
+                timestamp: None,
+            })
+            .unwrap();
+        processor
+            .process(&admin_transaction(TransactionType::Freeze))
+            .unwrap();
+        processor
+            .process(&admin_transaction(TransactionType::Unfreeze))
+            .unwrap();
+        for transaction_type in [TransactionType::Dispute, TransactionType::Chargeback] {
+            processor
+                .process(&Transaction {
+                    transaction_type,
+                    client: 1,
+                    transaction_id: 1,
+                    amount: None,
+                    timestamp: None,
+                })
+                .unwrap();
+        }
+
+        let err = processor
+            .process(&admin_transaction(TransactionType::Unfreeze))
+            .unwrap_err();
+        assert_eq!(err, TransactionProcessError::AccountLocked);
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary {
+                client: 1,
+                available: amount("0.0"),
+                held: amount("0.0"),
+                total: amount("0.0"),
+                locked: true,
+            }]
+        );
+    }
+
+    #[test]
+    fn dispute_metrics_should_count_successful_dispute_transactions() {
+        let mut processor = TransactionsProcessor::default();
+        for (transaction_type, client, transaction_id, amount) in [
+            (TransactionType::Deposit, 1, 1, Some(amount("10.0"))),
+            (TransactionType::Deposit, 2, 2, Some(amount("20.0"))),
+            (TransactionType::Dispute, 1, 1, None),
+            (TransactionType::Dispute, 2, 2, None),
+            (TransactionType::Resolve, 1, 1, None),
+            (TransactionType::Chargeback, 2, 2, None),
+            (TransactionType::Deposit, 3, 3, Some(amount("30.0"))),
+        ] {
+            processor
+                .process(&Transaction {
+                    transaction_type,
+                    client,
+                    transaction_id,
+                    amount,
+                    timestamp: None,
+                })
+                .unwrap();
+        }
+        // Failed attempts are not counted
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Resolve,
+                client: 1,
+                transaction_id: 1,
+                amount: None,
+                timestamp: None,
+            })
+            .unwrap_err();
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Dispute,
+                client: 3,
+                transaction_id: 4,
+                amount: None,
+                timestamp: None,
+            })
+            .unwrap_err();
+
+        let metrics = processor.dispute_metrics();
+        assert_eq!(
+            metrics.total,
+            DisputeCounts {
+                opened: 2,
+                resolved: 1,
+                charged_back: 1,
+            }
+        );
+        assert_eq!(
+            metrics.per_client,
+            HashMap::from([
+                (
+                    1,
+                    DisputeCounts {
+                        opened: 1,
+                        resolved: 1,
+                        charged_back: 0,
+                    }
+                ),
+                (
+                    2,
+                    DisputeCounts {
+                        opened: 1,
+                        resolved: 0,
+                        charged_back: 1,
+                    }
+                ),
+            ])
+        );
+    }
+
+    /// Store recording the calls made by the processor
+    #[derive(Default)]
+    struct RecordingStore {
+        store: HashMapHistoryStore<AmountType>,
+        calls: Vec<&'static str>,
+    }
+
+    impl HistoryStore<AmountType> for RecordingStore {
+        fn get(
+            &self,
+            client: ClientId,
+            transaction_id: TransactionId,
+        ) -> Option<HistoryRecord<AmountType>> {
+            self.store.get(client, transaction_id)
+        }
+
+        fn insert(
+            &mut self,
+            client: ClientId,
+            transaction_id: TransactionId,
+            record: HistoryRecord<AmountType>,
+        ) {
+            self.calls.push("insert");
+            self.store.insert(client, transaction_id, record)
+        }
+
+        fn update(
+            &mut self,
+            client: ClientId,
+            transaction_id: TransactionId,
+            record: HistoryRecord<AmountType>,
+        ) {
+            self.calls.push("update");
+            self.store.update(client, transaction_id, record)
+        }
+
+        fn client_records(
+            &self,
+            client: ClientId,
+        ) -> Vec<(TransactionId, HistoryRecord<AmountType>)> {
+            self.store.client_records(client)
+        }
+    }
+
+    #[test]
+    fn processor_should_keep_history_in_given_store() {
+        let mut processor =
+            TransactionsProcessor::builder().build_with_store(RecordingStore::default());
+        for (transaction_type, amount) in [
+            (TransactionType::Deposit, Some(amount("10.0"))),
+            (TransactionType::Dispute, None),
+        ] {
+            processor
+                .process(&Transaction {
+                    transaction_type,
+                    client: 1,
+                    transaction_id: 1,
+                    amount,
+                    timestamp: None,
+                })
+                .unwrap();
+        }
+
+        assert_eq!(processor.history.calls, vec!["insert", "update"]);
+        assert_eq!(
+            processor.history.get(1, 1),
+            Some(HistoryRecord {
+                amount: 100_000,
+                status: TransactionStatus::UnderDispute,
+                disputed: 100_000,
+                charged_back: 0,
+                disputes: 1,
+                sequence: 0,
+            })
+        );
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary {
+                client: 1,
+                available: amount("0.0"),
+                held: amount("10.0"),
+                total: amount("10.0"),
+                locked: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn on_lock_should_be_called_once_for_charged_back_client() {
+        let locks = Rc::new(RefCell::new(vec![]));
+        let recorded_locks = locks.clone();
+        let mut processor = TransactionsProcessor::builder()
+            .on_lock(move |client, transaction_id| {
+                recorded_locks.borrow_mut().push((client, transaction_id))
+            })
+            .build();
+        for (transaction_type, client, transaction_id, amount) in [
+            (TransactionType::Deposit, 1, 1, Some(amount("10.0"))),
+            (TransactionType::Deposit, 2, 2, Some(amount("10.0"))),
+            (TransactionType::Deposit, 2, 3, Some(amount("10.0"))),
+            (TransactionType::Dispute, 1, 1, None),
+            (TransactionType::Dispute, 2, 2, None),
+            (TransactionType::Dispute, 2, 3, None),
+            (TransactionType::Resolve, 1, 1, None),
+            (TransactionType::Chargeback, 2, 2, None),
+        ] {
+            processor
+                .process(&Transaction {
+                    transaction_type,
+                    client,
+                    transaction_id,
+                    amount,
+                    timestamp: None,
+                })
+                .unwrap();
+        }
+        // Account is already locked, so the second chargeback fails and does not notify
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Chargeback,
+                client: 2,
+                transaction_id: 3,
+                amount: None,
+                timestamp: None,
+            })
+            .unwrap_err();
+
+        assert_eq!(*locks.borrow(), vec![(2, 2)]);
+    }
+
+    #[test]
+    fn withdrawal_of_all_founds_should_mark_account_drained_to_zero() {
+        let mut processor = TransactionsProcessor::default();
+        for (transaction_type, transaction_id) in [
+            (TransactionType::Deposit, 1),
+            (TransactionType::Withdrawal, 2),
+        ] {
+            processor
+                .process(&Transaction {
+                    transaction_type,
+                    client: 1,
+                    transaction_id,
+                    amount: Some(amount("100.0")),
+                    timestamp: None,
+                })
+                .unwrap();
+        }
+        assert!(processor.is_drained_to_zero(1));
+        assert!(!processor.is_drained_to_zero(2));
+
+        // Partial withdrawal does not drain the account
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Deposit,
+                client: 2,
+                transaction_id: 1,
+                amount: Some(amount("100.0")),
+                timestamp: None,
+            })
+            .unwrap();
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Withdrawal,
+                client: 2,
+                transaction_id: 2,
+                amount: Some(amount("40.0")),
+                timestamp: None,
+            })
+            .unwrap();
+        assert!(!processor.is_drained_to_zero(2));
+
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Deposit,
+                client: 1,
+                transaction_id: 3,
+                amount: Some(amount("1.0")),
+                timestamp: None,
+            })
+            .unwrap();
+        assert!(!processor.is_drained_to_zero(1));
+    }
+
+    fn process_dispute_with_verified_amount(
+        dispute_amount: Option<Amount>,
+    ) -> Result<(), TransactionProcessError> {
+        let mut processor = TransactionsProcessor::builder()
+            .verify_dispute_amounts(true)
+            .build();
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Deposit,
+                client: 1,
+                transaction_id: 1,
+                amount: Some(amount("12.5")),
+                timestamp: None,
+            })
+            .unwrap();
+        processor.process(&Transaction {
+            transaction_type: TransactionType::Dispute,
+            client: 1,
+            transaction_id: 1,
+            amount: dispute_amount,
+            timestamp: None,
+        })
+    }
+
+    #[test]
+    fn dispute_with_matching_amount_should_succeed() {
+        assert_eq!(
+            process_dispute_with_verified_amount(Some(amount("12.5"))),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn dispute_with_mismatching_amount_should_fail() {
+        assert_eq!(
+            process_dispute_with_verified_amount(Some(amount("12.0"))),
+            Err(TransactionProcessError::DisputeAmountMismatch)
+        );
+    }
+
+    #[test]
+    fn dispute_without_amount_should_succeed_when_verifying_amounts() {
+        assert_eq!(process_dispute_with_verified_amount(None), Ok(()));
+    }
+
+    fn process_partial_dispute(
+        processor: &mut TransactionsProcessor,
+        dispute_amount: Amount,
+    ) -> Result<(), TransactionProcessError> {
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Deposit,
+                client: 1,
+                transaction_id: 1,
+                amount: Some(amount("100.0")),
+                timestamp: None,
+            })
+            .unwrap();
+        processor.process(&Transaction {
+            transaction_type: TransactionType::Dispute,
+            client: 1,
+            transaction_id: 1,
+            amount: Some(dispute_amount),
+            timestamp: None,
+        })
+    }
+
+    #[test]
+    fn partial_dispute_should_hold_only_disputed_amount() {
+        let mut processor = TransactionsProcessor::default();
+        process_partial_dispute(&mut processor, amount("40.0")).unwrap();
+
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary {
+                client: 1,
+                available: amount("60.0"),
+                held: amount("40.0"),
+                total: amount("100.0"),
+                locked: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn partial_dispute_of_invalid_amount_should_fail() {
+        assert_eq!(
+            process_partial_dispute(&mut TransactionsProcessor::default(), amount("100.5")),
+            Err(TransactionProcessError::DisputeAmountMismatch)
+        );
+        assert_eq!(
+            process_partial_dispute(&mut TransactionsProcessor::default(), amount("-1.0")),
+            Err(TransactionProcessError::NonPositiveAmountInTransaction)
+        );
+    }
+
+    #[test]
+    fn zero_amount_dispute_should_fail() {
+        let mut processor = TransactionsProcessor::default();
+        assert_eq!(
+            process_partial_dispute(&mut processor, amount("0.0")),
+            Err(TransactionProcessError::NonPositiveAmountInTransaction)
+        );
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary::new(1, amount("100.0"), amount("0.0"), false)]
+        );
+        assert_eq!(
+            process_dispute_with_verified_amount(Some(amount("0.0"))),
+            Err(TransactionProcessError::NonPositiveAmountInTransaction)
+        );
+    }
+
+    #[test]
+    fn resolve_of_partial_dispute_should_release_disputed_amount() {
+        let mut processor = TransactionsProcessor::default();
+        process_partial_dispute(&mut processor, amount("40.0")).unwrap();
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Resolve,
+                client: 1,
+                transaction_id: 1,
+                amount: None,
+                timestamp: None,
+            })
+            .unwrap();
+
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary::new(1, amount("100.0"), amount("0.0"), false)]
+        );
+    }
+
+    #[test]
+    fn chargeback_of_partial_dispute_should_remove_only_held_amount() {
+        let mut processor = TransactionsProcessor::default();
+        process_partial_dispute(&mut processor, amount("40.0")).unwrap();
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Chargeback,
+                client: 1,
+                transaction_id: 1,
+                amount: None,
+                timestamp: None,
+            })
+            .unwrap();
+
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary::new(1, amount("60.0"), amount("0.0"), true)]
+        );
+        assert_eq!(
+            processor.history.get(1, 1),
+            Some(HistoryRecord {
+                amount: 1_000_000,
+                status: TransactionStatus::Processed,
+                disputed: 0,
+                charged_back: 400_000,
+                disputes: 1,
+                sequence: 0,
+            })
+        );
+        assert_eq!(
+            processor.transaction_status(1, 1),
+            Some(TxStatusView::Processed {
+                amount: amount("60.0")
+            })
+        );
+    }
+
+    #[test]
+    fn rest_of_partially_charged_back_deposit_should_be_disputable() {
+        let mut processor = TransactionsProcessor::default();
+        process_partial_dispute(&mut processor, amount("40.0")).unwrap();
+        let process = |processor: &mut TransactionsProcessor, transaction_type| {
+            processor.process(&Transaction {
+                transaction_type,
+                client: 1,
+                transaction_id: 1,
+                amount: None,
+                timestamp: None,
+            })
+        };
+        process(&mut processor, TransactionType::Chargeback).unwrap();
+        processor.unlock(1);
+
+        process(&mut processor, TransactionType::Dispute).unwrap();
+        assert_eq!(
+            processor.transaction_status(1, 1),
+            Some(TxStatusView::UnderDispute {
+                amount: amount("60.0")
+            })
+        );
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary::new(1, amount("0.0"), amount("60.0"), false)]
+        );
+
+        process(&mut processor, TransactionType::Chargeback).unwrap();
+        assert_eq!(
+            processor.transaction_status(1, 1),
+            Some(TxStatusView::ChargeBack {
+                amount: amount("100.0")
+            })
+        );
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary::new(1, amount("0.0"), amount("0.0"), true)]
+        );
+        processor.unlock(1);
+        assert_eq!(
+            process(&mut processor, TransactionType::Dispute),
+            Err(TransactionProcessError::CannotDisputeChargedBack)
+        );
+    }
+
+    #[test]
+    fn partial_dispute_status_should_report_disputed_amount() {
+        let mut processor = TransactionsProcessor::default();
+        process_partial_dispute(&mut processor, amount("40.0")).unwrap();
+
+        assert_eq!(
+            processor.transaction_status(1, 1),
+            Some(TxStatusView::UnderDispute {
+                amount: amount("40.0")
+            })
+        );
+    }
+
+    #[test]
+    fn chargeback_of_partially_disputed_withdrawal_should_return_only_disputed_amount() {
+        let mut processor = processor_with_disputed_withdrawal();
+        let available_before = processor.summary()[0].available;
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Resolve,
+                client: 1,
+                transaction_id: 2,
+                amount: None,
+                timestamp: None,
+            })
+            .unwrap();
+        for transaction_type in [TransactionType::Dispute, TransactionType::Chargeback] {
+            processor
+                .process(&Transaction {
+                    transaction_type,
+                    client: 1,
+                    transaction_id: 2,
+                    amount: (transaction_type == TransactionType::Dispute).then_some(amount("5.0")),
+                    timestamp: None,
+                })
+                .unwrap();
+        }
+
+        let summary = &processor.summary()[0];
+        assert_eq!(summary.available, available_before + amount("5.0"));
+        assert_eq!(summary.held, amount("0.0"));
+        assert!(summary.locked);
+    }
+
+    #[test]
+    fn small_random_amounts_should_not_accumulate_rounding_residue() {
+        let mut processor = TransactionsProcessor::default();
+        let mut next = xorshift(0x9E37_79B9_7F4A_7C15);
+        // Exact expected available in 10^-4 units
+        let mut expected: i64 = 0;
+        for transaction_id in 0..20_000 {
+            // Amounts with 6 decimal places, ties are skipped as f64 cannot represent them exactly
+            let micros = next(10_000_000) as i64;
+            if micros % 100 == 50 {
+                continue;
+            }
+            let scaled = (micros + 50) / 100;
+            let withdrawal = next(3) == 0;
+            let result = processor.process(&Transaction {
+                transaction_type: if withdrawal {
+                    TransactionType::Withdrawal
+                } else {
+                    TransactionType::Deposit
+                },
+                client: 1,
+                transaction_id,
+                amount: Some(amount::from_scaled(micros, 6)),
+                timestamp: None,
+            });
+            if scaled == 0 {
+                assert_eq!(result, Err(TransactionProcessError::AmountRoundsToZero));
+            } else if withdrawal && scaled > expected {
+                assert_eq!(
+                    result,
+                    Err(TransactionProcessError::NotEnoughFoundsAvailable)
+                );
+            } else {
+                assert_eq!(result, Ok(()));
+                expected += if withdrawal { -scaled } else { scaled };
+            }
+        }
+
+        assert_eq!(processor.minor_units(1), (expected, 0, expected));
+        assert_eq!(
+            processor.summary()[0].available,
+            amount::from_scaled(expected, 4)
+        );
+    }
+
+    #[test]
+    fn sub_precision_amounts_should_be_rounded_per_transaction() {
+        let mut processor = TransactionsProcessor::default();
+        for transaction_id in 0..10_000 {
+            processor
+                .process(&Transaction {
+                    transaction_type: TransactionType::Deposit,
+                    client: 1,
+                    transaction_id,
+                    amount: Some(amount("0.00006")),
+                    timestamp: None,
+                })
+                .unwrap();
+        }
+
+        // Every deposit is rounded to 0.0001, the residue is not accumulated across transactions
+        assert_eq!(processor.minor_units(1), (10_000, 0, 10_000));
+        assert_eq!(processor.summary()[0].total, amount("1.0"));
+    }
+
+    #[test]
+    fn invariants_should_hold_for_random_transactions() {
+        let mut processor = TransactionsProcessor::builder()
+            .check_invariants(true)
+            .allow_withdrawal_disputes(true)
+            .build();
+        let mut next = xorshift(0x2545_F491_4F6C_DD1D);
+        let types = [
+            TransactionType::Deposit,
+            TransactionType::Withdrawal,
+            TransactionType::Dispute,
+            TransactionType::Resolve,
+            TransactionType::Chargeback,
+        ];
+        for _ in 0..10_000 {
+            let transaction_type = types[next(types.len() as u64) as usize];
+            let transaction = Transaction {
+                transaction_type,
+                client: next(5),
+                transaction_id: next(200),
+                amount: Some(amount::from_scaled(next(100_000), 2)),
+                timestamp: None,
+            };
+            match processor.process(&transaction) {
+                // Unlocked so the account keeps accepting transactions
+                Ok(()) if transaction_type == TransactionType::Chargeback => {
+                    processor.unlock(transaction.client)
+                }
+                // Violations are rejected, e.g. disputes of already withdrawn deposits
+                Ok(()) | Err(_) => {}
+            }
+            let (available, held, total) = processor.minor_units(transaction.client);
+            assert_eq!(total, available + held);
+            assert!(held >= 0 && held <= total, "{transaction:?}");
+        }
+        assert!(!processor.summary().is_empty());
+    }
+
+    #[test]
+    fn invariant_violation_should_leave_account_unchanged() {
+        let mut processor = TransactionsProcessor::builder()
+            .check_invariants(true)
+            .build();
+        process_all(
+            &mut processor,
+            &[
+                (TransactionType::Deposit, 1, 1, Some(amount("100.0"))),
+                (TransactionType::Withdrawal, 1, 2, Some(amount("100.0"))),
+            ],
+        );
+
+        // Holding the withdrawn deposit would make held exceed the total
+        assert_eq!(
+            processor.process(&Transaction {
+                transaction_type: TransactionType::Dispute,
+                client: 1,
+                transaction_id: 1,
+                amount: None,
+                timestamp: None,
+            }),
+            Err(TransactionProcessError::InvariantViolation)
+        );
+
+        assert_eq!(processor.minor_units(1), (0, 0, 0));
+        assert_eq!(
+            processor.history.get(1, 1).map(|record| record.status),
+            Some(TransactionStatus::Processed)
+        );
+        assert_eq!(processor.dispute_metrics().total, DisputeCounts::default());
+    }
+
+    #[test]
+    fn active_only_summary_should_skip_clients_with_only_rejected_transactions() {
+        let mut processor = TransactionsProcessor::builder().active_only(true).build();
+        let transactions = [
+            (TransactionType::Deposit, 1, 1, Some(amount("10.0"))),
+            // Client 2 has only rejected withdrawal
+            (TransactionType::Withdrawal, 2, 2, Some(amount("10.0"))),
+            // Client 3 has only frozen account
+            (TransactionType::Freeze, 3, 3, None),
+        ];
+        for (transaction_type, client, transaction_id, amount) in transactions {
+            let _ = processor.process(&Transaction {
+                transaction_type,
+                client,
+                transaction_id,
+                amount,
+                timestamp: None,
+            });
+        }
+
+        let clients = processor
+            .summary()
+            .iter()
+            .map(|summary| summary.client)
+            .collect::<Vec<_>>();
+        assert_eq!(clients, vec![1, 3]);
+    }
+
+    #[test]
+    fn transactions_over_max_amount_should_be_rejected() {
+        let mut processor = TransactionsProcessor::builder()
+            .max_transaction_amount(amount("1000000.0"))
+            .build();
+        let transaction = |transaction_type, transaction_id, amount| Transaction {
+            transaction_type,
+            client: 1,
+            transaction_id,
+            amount: Some(amount),
+            timestamp: None,
+        };
+
+        assert_eq!(
+            processor.process(&transaction(
+                TransactionType::Deposit,
+                1,
+                amount("2000000.0")
+            )),
+            Err(TransactionProcessError::AmountExceedsLimit)
+        );
+        assert_eq!(
+            processor.process(&transaction(
+                TransactionType::Deposit,
+                2,
+                amount("500000.0")
+            )),
+            Ok(())
+        );
+        assert_eq!(
+            processor.process(&transaction(
+                TransactionType::Deposit,
+                3,
+                amount("1000000.0")
+            )),
+            Ok(())
+        );
+        assert_eq!(
+            processor.process(&transaction(
+                TransactionType::Withdrawal,
+                4,
+                amount("1200000.0")
+            )),
+            Err(TransactionProcessError::AmountExceedsLimit)
+        );
+        assert_eq!(processor.summary()[0].available, amount("1500000.0"));
+    }
+
+    fn process_resolve_with_idempotent_resolve(
+        transaction_id: TransactionId,
+    ) -> (TransactionsProcessor, Result<(), TransactionProcessError>) {
+        let mut processor = TransactionsProcessor::builder()
+            .idempotent_resolve(true)
+            .build();
+        process_all(
+            &mut processor,
+            &[
+                (TransactionType::Deposit, 1, 1, Some(amount("100.0"))),
+                (TransactionType::Dispute, 1, 1, None),
+                (TransactionType::Resolve, 1, 1, None),
+            ],
+        );
+        let result = processor.process(&Transaction {
+            transaction_type: TransactionType::Resolve,
+            client: 1,
+            transaction_id,
+            amount: None,
+            timestamp: None,
+        });
+        (processor, result)
+    }
+
+    #[test]
+    fn repeated_resolve_should_be_no_op_when_idempotent() {
+        let (processor, result) = process_resolve_with_idempotent_resolve(1);
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary {
+                client: 1,
+                available: amount("100.0"),
+                held: amount("0.0"),
+                total: amount("100.0"),
+                locked: false,
+            }]
+        );
+        assert_eq!(processor.dispute_metrics().total.resolved, 1);
+    }
+
+    #[test]
+    fn resolve_of_unknown_transaction_should_fail_when_idempotent() {
+        let (_, result) = process_resolve_with_idempotent_resolve(2);
+
+        assert_eq!(result, Err(TransactionProcessError::TransactionNotFound));
+    }
+
+    /// Returns processor accepting deposits to locked accounts with account of client 1 locked by chargeback
+    fn locked_processor_allowing_deposits() -> TransactionsProcessor {
+        let mut processor = TransactionsProcessor::builder()
+            .allow_deposits_when_locked(true)
+            .build();
+        let transactions = [
+            (TransactionType::Deposit, 1, Some(amount("100.0"))),
+            (TransactionType::Deposit, 2, Some(amount("20.0"))),
+            (TransactionType::Dispute, 2, None),
+            (TransactionType::Chargeback, 2, None),
+        ];
+        for (transaction_type, transaction_id, amount) in transactions {
+            processor
+                .process(&Transaction {
+                    transaction_type,
+                    client: 1,
+                    transaction_id,
+                    amount,
+                    timestamp: None,
+                })
+                .unwrap();
+        }
+        processor
+    }
+
+    #[test]
+    fn deposit_to_locked_account_should_succeed_when_allowed() {
+        let mut processor = locked_processor_allowing_deposits();
+
+        processor
+            .process(&Transaction {
+                transaction_type: TransactionType::Deposit,
+                client: 1,
+                transaction_id: 3,
+                amount: Some(amount("50.0")),
+                timestamp: None,
+            })
+            .unwrap();
+
+        assert_eq!(
+            processor.summary(),
+            vec![ClientSummary {
+                client: 1,
+                available: amount("150.0"),
+                held: amount("0.0"),
+                total: amount("150.0"),
+                locked: true,
+            }]
+        );
+    }
+
+    #[test]
+    fn withdrawal_and_dispute_on_locked_account_should_fail_when_deposits_allowed() {
+        let mut processor = locked_processor_allowing_deposits();
+
+        assert_eq!(
+            processor.process(&Transaction {
+                transaction_type: TransactionType::Withdrawal,
+                client: 1,
+                transaction_id: 3,
+                amount: Some(amount("50.0")),
+                timestamp: None,
+            }),
+            Err(TransactionProcessError::AccountLocked)
+        );
+        assert_eq!(
+            processor.process(&Transaction {
+                transaction_type: TransactionType::Dispute,
+                client: 1,
+                transaction_id: 1,
+                amount: None,
+                timestamp: None,
+            }),
+            Err(TransactionProcessError::AccountLocked)
+        );
+    }
 }
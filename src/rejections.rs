@@ -0,0 +1,135 @@
+use std::io::Write;
+
+use anyhow::Context;
+use csv::{StringRecord, WriterBuilder};
+use serde::Serialize;
+
+use crate::models::{ClientId, Transaction, TransactionId, TransactionType};
+use crate::processor::TransactionProcessError;
+
+#[derive(Debug, Clone, PartialEq)]
+/// Transaction rejected by the processor together with the reason of the rejection
+pub struct ProcessingError {
+    pub transaction: Transaction,
+    pub error: TransactionProcessError,
+    /// Line of the transaction in the input, starting from 1 (header line)
+    pub line: u64,
+    /// Byte offset of the transaction in the input
+    pub byte: u64,
+    /// Fields of the input record as read, including the whitespace around them,
+    /// kept only with `ProcessingOptions::keep_raw_rejections`
+    pub raw: Option<StringRecord>,
+}
+
+#[derive(Serialize)]
+/// Single row of the rejections report
+struct RejectionRecord {
+    client: ClientId,
+    tx: TransactionId,
+    #[serde(rename = "type")]
+    transaction_type: TransactionType,
+    reason: String,
+}
+
+/// Writes the rejected transactions as CSV with `client,tx,type,reason` columns
+pub fn write_rejections<W: Write>(rejections: &[ProcessingError], out: W) -> anyhow::Result<()> {
+    let mut writer = WriterBuilder::new().has_headers(false).from_writer(out);
+    // Header is written explicitly so the report has it even if there are no rejections
+    writer
+        .write_record(["client", "tx", "type", "reason"])
+        .context("Failed to write rejections header")?;
+    for rejection in rejections {
+        writer
+            .serialize(RejectionRecord {
+                client: rejection.transaction.client,
+                tx: rejection.transaction.transaction_id,
+                transaction_type: rejection.transaction.transaction_type,
+                reason: rejection.error.to_string(),
+            })
+            .context("Failed to write rejection record")?;
+    }
+    writer.flush().context("Failed to flush rejections")?;
+    Ok(())
+}
+
+/// Writes the input records of the rejected transactions in the order and schema of the input, e.g. for resubmission
+/// The header is not written, so the rows can be appended to a file starting with the header of the input
+/// Fields are quoted only where necessary, so the quotes of the input that are not needed are dropped
+pub fn write_raw_rejections<W: Write>(
+    rejections: &[ProcessingError],
+    out: W,
+) -> anyhow::Result<()> {
+    let mut writer = WriterBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_writer(out);
+    for rejection in rejections {
+        let raw = rejection.raw.as_ref().with_context(|| {
+            format!(
+                "Rejection at line {} has no input record, raw rejections are not kept",
+                rejection.line
+            )
+        })?;
+        writer
+            .write_record(raw)
+            .context("Failed to write rejected record")?;
+    }
+    writer.flush().context("Failed to flush rejections")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amount::amount;
+
+    #[test]
+    fn write_rejections_should_write_client_tx_type_and_reason() {
+        let rejections = vec![
+            ProcessingError {
+                transaction: Transaction {
+                    transaction_type: TransactionType::Withdrawal,
+                    client: 1,
+                    transaction_id: 3,
+                    amount: Some(amount("100.0")),
+                    timestamp: None,
+                },
+                error: TransactionProcessError::NotEnoughFoundsAvailable,
+                line: 2,
+                byte: 25,
+                raw: None,
+            },
+            ProcessingError {
+                transaction: Transaction {
+                    transaction_type: TransactionType::Dispute,
+                    client: 2,
+                    transaction_id: 7,
+                    amount: None,
+                    timestamp: None,
+                },
+                error: TransactionProcessError::TransactionNotFound,
+                line: 3,
+                byte: 50,
+                raw: None,
+            },
+        ];
+
+        let mut out = vec![];
+        write_rejections(&rejections, &mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "client,tx,type,reason\n\
+            1,3,withdrawal,Not enough founds\n\
+            2,7,dispute,Transaction not found\n"
+        );
+    }
+
+    #[test]
+    fn write_rejections_without_rejections_should_write_only_header() {
+        let mut out = vec![];
+        write_rejections(&[], &mut out).unwrap();
+
+        assert_eq!(String::from_utf8(out).unwrap(), "client,tx,type,reason\n");
+    }
+}
@@ -0,0 +1,205 @@
+use std::io::Read;
+
+use anyhow::Context;
+use csv::{ReaderBuilder, Trim};
+
+use crate::diff::is_consistent;
+use crate::models::ClientSummary;
+use crate::processor::TransactionsProcessor;
+
+impl TransactionsProcessor {
+    /// Creates the processor with opening balances loaded from the CSV summary of the previous period
+    /// (`client,available,held,total,locked` columns, other columns are ignored), locked accounts stay locked
+    /// The history of the previous period is not known, so the disputes of its transactions fail as not found
+    /// and the held founds of the summary cannot be resolved or charged back
+    /// The opening balance is kept in the history as a settled deposit with `OPENING_BALANCE_TRANSACTION_ID`
+    pub fn from_summary_csv<R: Read>(input: R) -> anyhow::Result<Self> {
+        let mut reader = ReaderBuilder::new()
+            .trim(Trim::All)
+            .flexible(true)
+            .from_reader(input);
+        let mut processor = TransactionsProcessor::default();
+        for summary in reader.deserialize::<ClientSummary>() {
+            let summary = summary.context("Failed to deserialize client summary")?;
+            anyhow::ensure!(
+                is_consistent(&summary),
+                "Inconsistent balance of client {}, total is not available plus held",
+                summary.client
+            );
+            processor
+                .open_balance(&summary)
+                .with_context(|| format!("Failed to load balance of client {}", summary.client))?;
+        }
+        Ok(processor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amount::{amount, Amount};
+    use crate::history::TransactionStatus;
+    use crate::models::{Transaction, TransactionType};
+    use crate::processor::{
+        TransactionProcessError, TransactionRecordView, OPENING_BALANCE_TRANSACTION_ID,
+    };
+
+    fn transaction(
+        transaction_type: TransactionType,
+        client: u64,
+        transaction_id: u64,
+        amount: Option<Amount>,
+    ) -> Transaction {
+        Transaction {
+            transaction_type,
+            client,
+            transaction_id,
+            amount,
+            timestamp: None,
+        }
+    }
+
+    #[test]
+    fn from_summary_csv_should_continue_with_opening_balances() {
+        let summary = "client,available,held,total,locked\n\
+        1,100.0,20.0,120.0,false\n\
+        2,50.5,0.0,50.5,true\n";
+        let mut processor = TransactionsProcessor::from_summary_csv(summary.as_bytes()).unwrap();
+
+        processor
+            .process(&transaction(
+                TransactionType::Deposit,
+                1,
+                10,
+                Some(amount("30.0")),
+            ))
+            .unwrap();
+        processor
+            .process(&transaction(
+                TransactionType::Withdrawal,
+                1,
+                11,
+                Some(amount("110.0")),
+            ))
+            .unwrap();
+        processor
+            .process(&transaction(
+                TransactionType::Deposit,
+                3,
+                12,
+                Some(amount("5.0")),
+            ))
+            .unwrap();
+        assert_eq!(
+            processor.process(&transaction(
+                TransactionType::Deposit,
+                2,
+                13,
+                Some(amount("5.0"))
+            )),
+            Err(TransactionProcessError::AccountLocked)
+        );
+        // Transactions of the previous period are not known
+        assert_eq!(
+            processor.process(&transaction(TransactionType::Dispute, 1, 1, None)),
+            Err(TransactionProcessError::TransactionNotFound)
+        );
+
+        assert_eq!(
+            processor.summary(),
+            vec![
+                ClientSummary {
+                    client: 1,
+                    available: amount("20.0"),
+                    held: amount("20.0"),
+                    total: amount("40.0"),
+                    locked: false,
+                },
+                ClientSummary {
+                    client: 2,
+                    available: amount("50.5"),
+                    held: amount("0.0"),
+                    total: amount("50.5"),
+                    locked: true,
+                },
+                ClientSummary {
+                    client: 3,
+                    available: amount("5.0"),
+                    held: amount("0.0"),
+                    total: amount("5.0"),
+                    locked: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn from_summary_csv_should_keep_opening_balance_in_history() {
+        let summary = "client,available,held,total,locked\n\
+        1,100.0,20.0,120.0,false\n";
+        let mut processor = TransactionsProcessor::from_summary_csv(summary.as_bytes()).unwrap();
+        processor
+            .process(&transaction(
+                TransactionType::Deposit,
+                1,
+                1,
+                Some(amount("30.0")),
+            ))
+            .unwrap();
+
+        assert_eq!(
+            processor.client_history(1),
+            vec![
+                TransactionRecordView {
+                    transaction_id: OPENING_BALANCE_TRANSACTION_ID,
+                    amount: amount("120.0"),
+                    status: TransactionStatus::Settled,
+                },
+                TransactionRecordView {
+                    transaction_id: 1,
+                    amount: amount("30.0"),
+                    status: TransactionStatus::Processed,
+                },
+            ]
+        );
+        assert_eq!(
+            processor.process(&transaction(
+                TransactionType::Dispute,
+                1,
+                OPENING_BALANCE_TRANSACTION_ID,
+                None
+            )),
+            Err(TransactionProcessError::CannotDisputeSettled)
+        );
+    }
+
+    // `Decimal` amounts cannot be NaN
+    #[cfg(not(feature = "decimal"))]
+    #[test]
+    fn from_summary_csv_with_nan_total_should_fail() {
+        let summary = "client,available,held,total,locked\n\
+        1,0.0,0.0,NaN,false\n";
+
+        let err = TransactionsProcessor::from_summary_csv(summary.as_bytes())
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            err.to_string(),
+            "Inconsistent balance of client 1, total is not available plus held"
+        );
+    }
+
+    #[test]
+    fn from_summary_csv_with_duplicated_client_should_fail() {
+        let summary = "client,available,held,total,locked\n\
+        1,100.0,0.0,100.0,false\n\
+        1,10.0,0.0,10.0,false\n";
+
+        let err = TransactionsProcessor::from_summary_csv(summary.as_bytes())
+            .err()
+            .unwrap();
+
+        assert_eq!(err.to_string(), "Failed to load balance of client 1");
+    }
+}
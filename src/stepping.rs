@@ -0,0 +1,290 @@
+use std::cell::Cell;
+use std::io::Read;
+use std::rc::Rc;
+
+use anyhow::Context;
+use csv::{Position, StringRecord, StringRecordsIntoIter};
+use log::{error, info};
+
+use crate::input::{normalize_amount, RecordBytesLimit, UnknownTypePolicy};
+use crate::models::{
+    ClientId, Transaction, TransactionType, AMOUNT_HEADER, INPUT_HEADERS, TYPE_HEADER,
+};
+use crate::options::ProcessingOptions;
+use crate::output::render_summary;
+use crate::processor::TransactionsProcessor;
+use crate::rejections::ProcessingError;
+use crate::{options_reader, ProcessingReport};
+
+/// Processes CSV records one at a time, keeping the state of processing between the calls
+pub struct RecordProcessor<'a> {
+    options: &'a ProcessingOptions,
+    client_filter: Box<dyn Fn(ClientId) -> bool + 'a>,
+    headers: StringRecord,
+    amount_index: Option<usize>,
+    type_index: Option<usize>,
+    processor: TransactionsProcessor,
+    rejections: Vec<ProcessingError>,
+    truncated: bool,
+    locked_client: Rc<Cell<Option<ClientId>>>,
+    /// Number of records passed to `process_step` before the processing was truncated
+    records: usize,
+}
+
+impl<'a> RecordProcessor<'a> {
+    /// Creates the processor of records with the given header
+    pub fn new(headers: StringRecord, options: &'a ProcessingOptions) -> Self {
+        Self::with_client_filter(headers, options, |_| true)
+    }
+
+    /// Creates the processor of records skipping the transactions of clients not matching the predicate
+    pub fn with_client_filter(
+        headers: StringRecord,
+        options: &'a ProcessingOptions,
+        client_filter: impl Fn(ClientId) -> bool + 'a,
+    ) -> Self {
+        let locked_client = Rc::new(Cell::new(None));
+        let mut builder = TransactionsProcessor::builder().active_only(options.active_only);
+        if options.halt_on_lock {
+            let locked_client = locked_client.clone();
+            builder = builder.on_lock(move |client, _| {
+                // Only the first lock is reported, processing stops right after it
+                if locked_client.get().is_none() {
+                    locked_client.set(Some(client));
+                }
+            });
+        }
+        let mut processor = Self {
+            options,
+            client_filter: Box::new(client_filter),
+            headers: StringRecord::new(),
+            amount_index: None,
+            type_index: None,
+            processor: builder.build(),
+            rejections: vec![],
+            truncated: false,
+            locked_client,
+            records: 0,
+        };
+        processor.set_headers(headers);
+        processor
+    }
+
+    /// Replaces the header used for the following records, e.g. when continuing with the next input file
+    pub(crate) fn set_headers(&mut self, mut headers: StringRecord) {
+        headers.trim();
+        self.amount_index = headers.iter().position(|header| header == AMOUNT_HEADER);
+        self.type_index = headers.iter().position(|header| header == TYPE_HEADER);
+        self.headers = headers;
+    }
+
+    /// Processes a single record, rejected transactions are collected for the report
+    /// Returns error if the record is malformed, processing should not be continued then
+    pub fn process_step(&mut self, record: StringRecord) -> anyhow::Result<()> {
+        if self.truncated || self.stop_at_limit() {
+            return Ok(());
+        }
+        self.records += 1;
+        let (record, raw) = if self.options.keep_raw_rejections {
+            let mut trimmed = record.clone();
+            trimmed.trim();
+            (trimmed, Some(record))
+        } else {
+            (record, None)
+        };
+        // Records read from the reader always have position
+        let position = record.position().cloned().unwrap_or_else(Position::new);
+        // Readers of the crate enforce the limit while reading, this covers records passed here directly
+        if let Some(max_record_bytes) = self.options.max_record_bytes {
+            let record_bytes = record.as_slice().len();
+            if record_bytes > max_record_bytes {
+                anyhow::bail!(
+                    "Record at line {} has {} bytes, exceeding the limit of {} bytes",
+                    position.line(),
+                    record_bytes,
+                    max_record_bytes
+                );
+            }
+        }
+        // Blank rows (e.g. `,,,` at the end of spreadsheet exports) are not transactions
+        if record.iter().all(str::is_empty) {
+            info!("Skipping empty record at line {}", position.line());
+            return Ok(());
+        }
+        if self.options.skip_repeated_headers && record.iter().eq(self.headers.iter()) {
+            info!("Skipping repeated header {:?}", record);
+            return Ok(());
+        }
+        if self.options.on_unknown_type == UnknownTypePolicy::Skip {
+            let transaction_type = self.type_index.and_then(|index| record.get(index));
+            if let Some(Err(err)) = transaction_type.map(str::parse::<TransactionType>) {
+                info!("Skipping record at line {}: {}", position.line(), err);
+                return Ok(());
+            }
+        }
+        // Unquoted amount with ',' decimal separator is split into an extra field, it would be silently truncated
+        if record.len() > self.headers.len() {
+            anyhow::bail!(
+                "Record at line {} has {} fields while the header has {}, amounts with ',' decimal separator have to be quoted, e.g. \"12,50\"",
+                position.line(),
+                record.len(),
+                self.headers.len()
+            );
+        }
+        let record = normalize_amount(record, self.amount_index, self.options.decimal_separator)?;
+        let mut transaction: Transaction = record
+            .deserialize(Some(&self.headers))
+            .context("Failed to deserialize transaction")?;
+        if !(self.client_filter)(transaction.client) {
+            info!("Skipping filtered out transaction {:?}", transaction);
+            return Ok(());
+        }
+        if let Some(client) = self.options.client_map.get(&transaction.client) {
+            transaction.client = *client;
+        }
+        // The errors from transactions are ignored in this function as if transaction has never happened
+        match self.processor.process(&transaction) {
+            Ok(()) => {
+                info!("Successfully processed transaction {:?}", transaction);
+                if let Some(client) = self.locked_client.get() {
+                    error!(
+                        "Stopping processing after locking account of client {}",
+                        client
+                    );
+                    self.truncated = true;
+                }
+            }
+            Err(err) => {
+                error!(
+                    "Failed to process transaction {:?}, error: {}",
+                    transaction, err
+                );
+                self.rejections.push(ProcessingError {
+                    transaction,
+                    error: err,
+                    line: position.line(),
+                    byte: position.byte(),
+                    raw,
+                });
+                if self
+                    .options
+                    .max_rejections
+                    .is_some_and(|max_rejections| self.rejections.len() >= max_rejections)
+                {
+                    error!(
+                        "Stopping processing after {} rejections",
+                        self.rejections.len()
+                    );
+                    self.truncated = true;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns true if processing was stopped and further records are ignored (e.g. after too many rejections)
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Returns true if the limit of records is reached, the further records are not processed then
+    fn limit_reached(&self) -> bool {
+        self.options
+            .limit
+            .is_some_and(|limit| self.records >= limit)
+    }
+
+    /// Truncates the processing if the limit of records is reached, returns true if it is truncated then
+    /// Called only when there is a further record, input with exactly `limit` records is not truncated
+    fn stop_at_limit(&mut self) -> bool {
+        if self.limit_reached() {
+            info!("Stopping processing after {} records", self.records);
+            self.truncated = true;
+        }
+        self.truncated
+    }
+
+    /// Returns summary of all client accounts after the records processed so far
+    pub fn summary(&self) -> anyhow::Result<String> {
+        render_summary(&self.processor, self.options)
+    }
+
+    /// Finishes processing, returning the summary of all the records processed so far
+    pub fn finish(self) -> anyhow::Result<ProcessingReport> {
+        Ok(ProcessingReport {
+            summary: render_summary(&self.processor, self.options)?,
+            rejections: self.rejections,
+            truncated: self.truncated,
+            halted_by: self.locked_client.get(),
+        })
+    }
+}
+
+/// Returns the header of the reader
+/// Records of headerless input are deserialized positionally as the standard columns
+pub(crate) fn headers<R: Read>(reader: &mut csv::Reader<R>) -> anyhow::Result<StringRecord> {
+    if reader.has_headers() {
+        Ok(reader.headers().context("Failed to read headers")?.clone())
+    } else {
+        Ok(StringRecord::from(INPUT_HEADERS.to_vec()))
+    }
+}
+
+/// Pull-driven processing of the CSV input, processing one record per call of `step`
+/// Allows the caller to yield (e.g. `.await`) between the records instead of blocking until the whole input is read
+pub struct ProcessingDriver<'a, R: Read> {
+    records: StringRecordsIntoIter<RecordBytesLimit<R>>,
+    processor: RecordProcessor<'a>,
+}
+
+impl<'a, R: Read> ProcessingDriver<'a, R> {
+    /// Creates the driver reading the header from the first line of the input, unless the input is headerless
+    pub fn new(input: R, options: &'a ProcessingOptions) -> anyhow::Result<Self> {
+        Self::from_reader(options_reader(input, options), options, |_| true)
+    }
+
+    pub(crate) fn from_reader(
+        mut reader: csv::Reader<RecordBytesLimit<R>>,
+        options: &'a ProcessingOptions,
+        client_filter: impl Fn(ClientId) -> bool + 'a,
+    ) -> anyhow::Result<Self> {
+        let headers = headers(&mut reader)?;
+        Ok(Self {
+            records: reader.into_records(),
+            processor: RecordProcessor::with_client_filter(headers, options, client_filter),
+        })
+    }
+
+    /// Reads and processes the next record
+    /// Returns false when there are no more records to process (end of input or processing truncated)
+    pub fn step(&mut self) -> anyhow::Result<bool> {
+        if self.processor.is_truncated() {
+            return Ok(false);
+        }
+        // Record after the limit is only checked for existence, it is not parsed (e.g. broken line of a sample)
+        if self.processor.limit_reached() {
+            if self.records.next().is_some() {
+                self.processor.stop_at_limit();
+            }
+            return Ok(false);
+        }
+        match self.records.next() {
+            Some(record) => {
+                let record = record.context("Failed to read record")?;
+                self.processor.process_step(record)?;
+                Ok(!self.processor.is_truncated())
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Returns summary of all client accounts after the records processed so far
+    pub fn summary(&self) -> anyhow::Result<String> {
+        self.processor.summary()
+    }
+
+    /// Finishes processing, returning the summary of all the records processed so far
+    pub fn finish(self) -> anyhow::Result<ProcessingReport> {
+        self.processor.finish()
+    }
+}
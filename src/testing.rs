@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+
+use crate::amount;
+use crate::models::{ClientId, Transaction, TransactionId, TransactionType};
+
+/// Amounts of the generated transactions are multiples of 0.01
+const AMOUNT_DECIMAL_PLACES: u32 = 2;
+/// Maximum amount of the generated deposit in cents
+const MAX_DEPOSIT_CENTS: i64 = 100_000;
+
+/// Deterministic pseudo-random number generator (SplitMix64), good enough for synthetic inputs
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns number in range `0..bound`
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+}
+
+#[derive(Default)]
+/// State of the generated client account, used to generate only valid transactions
+struct GeneratedClient {
+    available: i64,
+    /// Deposits that can be disputed as (transaction id, amount in cents)
+    deposits: Vec<(TransactionId, i64)>,
+    /// Disputed deposits that can be resolved
+    disputes: Vec<(TransactionId, i64)>,
+}
+
+/// Generates a deterministic mix of deposits, withdrawals, disputes and resolves for benchmarks
+/// Withdrawals, disputes and resolves refer only to valid state of the accounts, so the transactions
+/// are accepted by the processor with default options
+pub fn generate_transactions(num_clients: u64, num_txns: usize, seed: u64) -> Vec<Transaction> {
+    let mut rng = Rng(seed);
+    let mut clients: HashMap<ClientId, GeneratedClient> = HashMap::new();
+    let mut next_transaction_id: TransactionId = 1;
+    let mut transactions = Vec::with_capacity(num_txns);
+    for _ in 0..num_txns {
+        let client_id = rng.below(num_clients.max(1)) + 1;
+        let client = clients.entry(client_id).or_default();
+        // 60% deposits, 25% withdrawals, 10% disputes and 5% resolves when the account allows them
+        let (transaction_type, transaction_id, cents) = match rng.below(100) {
+            60..=84 if client.available > 0 => {
+                let cents = rng.below(client.available as u64) as i64 + 1;
+                client.available -= cents;
+                (TransactionType::Withdrawal, None, Some(cents))
+            }
+            85..=94 if !client.deposits.is_empty() => {
+                let index = rng.below(client.deposits.len() as u64) as usize;
+                let (transaction_id, cents) = client.deposits[index];
+                if cents <= client.available {
+                    client.deposits.swap_remove(index);
+                    client.disputes.push((transaction_id, cents));
+                    client.available -= cents;
+                    (TransactionType::Dispute, Some(transaction_id), None)
+                } else {
+                    deposit(&mut rng, client, next_transaction_id)
+                }
+            }
+            95.. if !client.disputes.is_empty() => {
+                let index = rng.below(client.disputes.len() as u64) as usize;
+                let (transaction_id, cents) = client.disputes.swap_remove(index);
+                client.deposits.push((transaction_id, cents));
+                client.available += cents;
+                (TransactionType::Resolve, Some(transaction_id), None)
+            }
+            _ => deposit(&mut rng, client, next_transaction_id),
+        };
+        let transaction_id = transaction_id.unwrap_or_else(|| {
+            next_transaction_id += 1;
+            next_transaction_id - 1
+        });
+        transactions.push(Transaction {
+            transaction_type,
+            client: client_id,
+            transaction_id,
+            amount: cents.map(|cents| amount::from_scaled(cents, AMOUNT_DECIMAL_PLACES)),
+            timestamp: None,
+        });
+    }
+    transactions
+}
+
+/// Generates deposit with a new transaction id, returned as type, existing transaction id (none) and amount
+fn deposit(
+    rng: &mut Rng,
+    client: &mut GeneratedClient,
+    transaction_id: TransactionId,
+) -> (TransactionType, Option<TransactionId>, Option<i64>) {
+    let cents = rng.below(MAX_DEPOSIT_CENTS as u64) as i64 + 1;
+    client.available += cents;
+    client.deposits.push((transaction_id, cents));
+    (TransactionType::Deposit, None, Some(cents))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::TransactionsProcessor;
+
+    #[test]
+    fn generate_transactions_should_be_deterministic() {
+        let transactions = generate_transactions(10, 1000, 42);
+
+        assert_eq!(transactions.len(), 1000);
+        assert_eq!(transactions, generate_transactions(10, 1000, 42));
+        assert_ne!(transactions, generate_transactions(10, 1000, 43));
+    }
+
+    #[test]
+    fn generated_transactions_should_be_accepted_by_processor() {
+        let transactions = generate_transactions(20, 5000, 7);
+        let mut processor = TransactionsProcessor::default();
+
+        let rejected = transactions
+            .iter()
+            .filter(|transaction| processor.process(transaction).is_err())
+            .count();
+
+        assert!(rejected * 100 < transactions.len(), "{rejected} rejected");
+        for transaction_type in [
+            TransactionType::Deposit,
+            TransactionType::Withdrawal,
+            TransactionType::Dispute,
+            TransactionType::Resolve,
+        ] {
+            assert!(transactions
+                .iter()
+                .any(|transaction| transaction.transaction_type == transaction_type));
+        }
+        assert!(processor.summary().len() <= 20);
+    }
+}
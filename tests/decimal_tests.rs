@@ -0,0 +1,232 @@
+#![cfg(feature = "decimal")]
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use transaction_processor::{
+    process_transactions, process_transactions_from_reader, process_transactions_str,
+    process_transactions_with_options, Amount, ClientSummary, LineTerminator, OutputFormat,
+    ProcessingOptions, QuoteStyle, Transaction, TransactionProcessError, TransactionType,
+    TransactionsProcessor, TxStatusView,
+};
+
+fn amount(v: &str) -> Amount {
+    Amount::from_str(v).unwrap()
+}
+
+fn test_directory() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test_cases")
+}
+
+fn process_all(
+    processor: &mut TransactionsProcessor,
+    transactions: &[(TransactionType, u64, &str)],
+) {
+    for (transaction_type, transaction_id, value) in transactions {
+        processor
+            .process(&Transaction {
+                transaction_type: *transaction_type,
+                client: 1,
+                transaction_id: *transaction_id,
+                amount: (!value.is_empty()).then(|| amount(value)),
+                timestamp: None,
+            })
+            .unwrap();
+    }
+}
+
+#[test]
+fn deposits_should_be_summed_exactly() {
+    let mut processor = TransactionsProcessor::default();
+    process_all(
+        &mut processor,
+        &[
+            (TransactionType::Deposit, 1, "0.1"),
+            (TransactionType::Deposit, 2, "0.2"),
+        ],
+    );
+
+    assert_eq!(
+        processor.summary(),
+        vec![ClientSummary {
+            client: 1,
+            available: amount("0.3"),
+            held: amount("0"),
+            total: amount("0.3"),
+            locked: false,
+        }]
+    );
+}
+
+#[test]
+fn dispute_and_chargeback_should_carry_exact_amounts() {
+    let mut processor = TransactionsProcessor::default();
+    process_all(
+        &mut processor,
+        &[
+            (TransactionType::Deposit, 1, "0.1"),
+            (TransactionType::Deposit, 2, "0.2"),
+            (TransactionType::Dispute, 2, ""),
+        ],
+    );
+    assert_eq!(processor.summary()[0].held, amount("0.2"));
+    assert_eq!(processor.summary()[0].total, amount("0.3"));
+
+    process_all(&mut processor, &[(TransactionType::Chargeback, 2, "")]);
+    assert_eq!(
+        processor.summary(),
+        vec![ClientSummary {
+            client: 1,
+            available: amount("0.1"),
+            held: amount("0"),
+            total: amount("0.1"),
+            locked: true,
+        }]
+    );
+    assert_eq!(
+        processor.transaction_status(1, 2),
+        Some(TxStatusView::ChargeBack {
+            amount: amount("0.2")
+        })
+    );
+}
+
+#[test]
+fn csv_summary_should_contain_exact_decimals() {
+    let result = process_transactions_str(
+        "type, client, tx, amount\n\
+        deposit, 1, 1, 0.1\n\
+        deposit, 1, 2, 0.2\n\
+        withdrawal, 1, 3, 0.05\n",
+    )
+    .unwrap();
+
+    let expected = "client,available,held,total,locked\n\
+    1,0.25,0,0.25,false\n";
+    assert_eq!(result, expected)
+}
+
+#[test]
+fn integer_like_amounts_should_be_written_the_same() {
+    let summaries = ["100", "100.0", "100.0000"].map(|value| {
+        process_transactions_str(&format!(
+            "type, client, tx, amount\ndeposit, 1, 1, {value}\n"
+        ))
+        .unwrap()
+    });
+
+    assert_eq!(
+        summaries[0],
+        "client,available,held,total,locked\n1,100,0,100,false\n"
+    );
+    assert!(summaries.iter().all(|summary| summary == &summaries[0]));
+}
+
+#[test]
+fn amounts_should_be_rounded_to_processor_precision() {
+    let deposit = |transaction_id, value| Transaction {
+        transaction_type: TransactionType::Deposit,
+        client: 1,
+        transaction_id,
+        amount: Some(amount(value)),
+        timestamp: None,
+    };
+    let mut processor = TransactionsProcessor::default();
+
+    // The processor keeps 4 decimal places also with `Decimal` amounts
+    assert_eq!(
+        processor.process(&deposit(1, "0.00001")),
+        Err(TransactionProcessError::AmountRoundsToZero)
+    );
+    processor.process(&deposit(2, "0.12345")).unwrap();
+
+    assert_eq!(processor.summary()[0].available, amount("0.1235"));
+}
+
+#[test]
+fn fixtures_should_give_summaries_with_exact_decimals() {
+    let cases = [
+        (
+            "single_client_deposits.csv",
+            "client,available,held,total,locked\n1,130,0,130,false\n",
+        ),
+        (
+            "single_client_deposits_and_withdrawals.csv",
+            "client,available,held,total,locked\n1,40,0,40,false\n",
+        ),
+        (
+            "single_client_dispute.csv",
+            "client,available,held,total,locked\n1,100,20,120,false\n",
+        ),
+        (
+            "single_client_dispute_resolved.csv",
+            "client,available,held,total,locked\n1,120,0,120,false\n",
+        ),
+        (
+            "single_client_dispute_chargeback.csv",
+            "client,available,held,total,locked\n1,100,0,100,true\n",
+        ),
+        (
+            "multiple_users_all_types_of_transactions.csv",
+            "client,available,held,total,locked\n\
+            1,20,0,20,false\n\
+            2,50,100,150,false\n\
+            3,-300,0,-300,true\n\
+            4,400,0,400,true\n",
+        ),
+        (
+            "shuffled_columns.csv",
+            "client,available,held,total,locked\n1,90,20,110,false\n2,5,0,5,false\n",
+        ),
+        (
+            "precision_check.csv",
+            "client,available,held,total,locked\n1,0,0,0,false\n",
+        ),
+    ];
+
+    for (filename, expected) in cases {
+        let result = process_transactions(test_directory().join(filename)).unwrap();
+        assert_eq!(result, expected, "{filename}");
+    }
+}
+
+#[test]
+fn quoted_crlf_summary_should_contain_exact_decimals() {
+    let options = ProcessingOptions::new()
+        .quote_style(QuoteStyle::Always)
+        .line_terminator(LineTerminator::CrLf);
+    let result = process_transactions_with_options(
+        test_directory().join("single_client_dispute.csv"),
+        &options,
+    )
+    .unwrap();
+
+    assert_eq!(
+        result.summary,
+        "\"client\",\"available\",\"held\",\"total\",\"locked\"\r\n\
+        \"1\",\"100\",\"20\",\"120\",\"false\"\r\n"
+    );
+}
+
+#[test]
+fn ndjson_summary_should_contain_amounts_as_strings() {
+    let input = "type, client, tx, amount\n\
+        deposit, 1, 1, 10.0\n\
+        deposit, 2, 2, 0.1\n\
+        deposit, 2, 3, 0.2\n";
+    let options = ProcessingOptions::new()
+        .output_format(OutputFormat::Ndjson)
+        .client_currencies(HashMap::from([(2, "EUR".to_string())]));
+    let result = process_transactions_from_reader(input.as_bytes(), &options).unwrap();
+
+    let mut lines = result.summary.lines().collect::<Vec<_>>();
+    lines.sort();
+    assert_eq!(
+        lines,
+        vec![
+            "{\"client\":1,\"available\":\"10\",\"held\":\"0\",\"total\":\"10\",\"currency\":\"\",\"locked\":false}",
+            "{\"client\":2,\"available\":\"0.3\",\"held\":\"0\",\"total\":\"0.3\",\"currency\":\"EUR\",\"locked\":false}",
+        ]
+    );
+}
@@ -1,7 +1,31 @@
+// Some of the functions are used only by the tests of summaries written with `f64` amounts
+#![cfg_attr(feature = "decimal", allow(unused_imports))]
+
+use std::collections::HashMap;
 use std::fs;
+use std::io::Read;
 use std::path::PathBuf;
+use std::str::FromStr;
+
+use csv::{ReaderBuilder, Trim};
+use transaction_processor::{
+    process_reader, process_transactions, process_transactions_dir, process_transactions_filtered,
+    process_transactions_from_reader, process_transactions_from_reader_with_progress,
+    process_transactions_str, process_transactions_time_ordered,
+    process_transactions_with_checkpoints, process_transactions_with_client_map,
+    process_transactions_with_options, process_transactions_with_rejections, run,
+    try_process_transactions, write_raw_rejections, Amount, AmountFormat, ClientSummary,
+    DecimalSeparator, LineTerminator, OutputFormat, ProcessFileError, ProcessingDriver,
+    ProcessingOptions, QuoteStyle, Transaction, TransactionProcessError, TransactionType,
+    TransactionsProcessor, UnknownTypePolicy,
+};
+
+// Tests comparing the written summary expect the formatting of `f64` amounts,
+// the summaries written with `Decimal` amounts are tested in `decimal_tests.rs`
 
-use transaction_processor::process_transactions;
+fn amount(v: &str) -> Amount {
+    Amount::from_str(v).unwrap()
+}
 
 fn test_directory() -> PathBuf {
     PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test_cases")
@@ -14,6 +38,7 @@ fn process_transactions_no_transactions_test() {
     let expected = "client,available,held,total,locked";
     assert_eq!(result, expected)
 }
+#[cfg(not(feature = "decimal"))]
 #[test]
 fn process_transactions_single_client_deposits_test() {
     let result = process_transactions(test_directory().join("single_client_deposits.csv")).unwrap();
@@ -23,6 +48,7 @@ fn process_transactions_single_client_deposits_test() {
     assert_eq!(result, expected)
 }
 
+#[cfg(not(feature = "decimal"))]
 #[test]
 // Multiple transactions to check precision of calculations
 fn precision_check() {
@@ -33,6 +59,7 @@ fn precision_check() {
     assert_eq!(result, expected)
 }
 
+#[cfg(not(feature = "decimal"))]
 #[test]
 fn process_single_client_deposits_and_withdrawals_test() {
     let result =
@@ -48,6 +75,7 @@ fn process_single_client_deposits_and_withdrawals_test() {
     assert_eq!(result, expected)
 }
 
+#[cfg(not(feature = "decimal"))]
 #[test]
 fn process_single_client_dispute_test() {
     let result = process_transactions(test_directory().join("single_client_dispute.csv")).unwrap();
@@ -59,6 +87,7 @@ fn process_single_client_dispute_test() {
     assert_eq!(result, expected)
 }
 
+#[cfg(not(feature = "decimal"))]
 #[test]
 fn process_single_client_dispute_chargeback_test() {
     let result =
@@ -73,6 +102,7 @@ fn process_single_client_dispute_chargeback_test() {
     assert_eq!(result, expected)
 }
 
+#[cfg(not(feature = "decimal"))]
 #[test]
 fn process_single_client_dispute_resolved_test() {
     let result =
@@ -85,6 +115,7 @@ fn process_single_client_dispute_resolved_test() {
             .replace("\r\n", "\n");
     assert_eq!(result, expected)
 }
+#[cfg(not(feature = "decimal"))]
 #[test]
 fn process_multiple_users_all_types_of_transactions_test() {
     // This test has 4 client
@@ -104,3 +135,1077 @@ fn process_multiple_users_all_types_of_transactions_test() {
     .replace("\r\n", "\n");
     assert_eq!(result, expected)
 }
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn process_transactions_with_shuffled_columns_test() {
+    // Columns are mapped by header names, so their order in the file does not matter
+    let result = process_transactions(test_directory().join("shuffled_columns.csv")).unwrap();
+
+    let expected = fs::read_to_string(test_directory().join("expected_shuffled_columns.csv"))
+        .unwrap()
+        // Hack for windows
+        .replace("\r\n", "\n");
+    assert_eq!(result, expected)
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn process_transactions_str_test() {
+    let result = process_transactions_str(
+        "type, client, tx, amount\n\
+        deposit, 1, 1, 10.0\n\
+        deposit, 2, 2, 5.5\n\
+        withdrawal, 1, 3, 2.5\n\
+        dispute, 2, 2\n",
+    )
+    .unwrap();
+
+    let expected = "client,available,held,total,locked\n\
+    1,7.5,0.0,7.5,false\n\
+    2,0.0,5.5,5.5,false\n";
+    assert_eq!(result, expected)
+}
+
+#[test]
+fn process_transactions_str_only_header_test() {
+    let result = process_transactions_str("type, client, tx, amount\n").unwrap();
+
+    let expected = "client,available,held,total,locked";
+    assert_eq!(result, expected)
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn process_transactions_with_repeated_header_test() {
+    let options = ProcessingOptions::new().skip_repeated_headers(true);
+    let result =
+        process_transactions_with_options(test_directory().join("repeated_header.csv"), &options)
+            .unwrap();
+
+    let expected = fs::read_to_string(test_directory().join("expected_repeated_header.csv"))
+        .unwrap()
+        // Hack for windows
+        .replace("\r\n", "\n");
+    assert_eq!(result.summary, expected);
+    assert!(result.rejections.is_empty());
+}
+
+#[test]
+fn process_transactions_with_repeated_header_should_fail_by_default_test() {
+    assert!(process_transactions(test_directory().join("repeated_header.csv")).is_err());
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn process_single_client_dispute_with_net_test() {
+    let options = ProcessingOptions::new().include_net(true);
+    let result = process_transactions_with_options(
+        test_directory().join("single_client_dispute.csv"),
+        &options,
+    )
+    .unwrap();
+
+    // net is available minus held
+    let expected = "client,available,held,total,locked,net\n\
+    1,100.0,20.0,120.0,false,80.0\n";
+    assert_eq!(result.summary, expected)
+}
+
+#[test]
+fn process_single_client_dispute_in_minor_units_test() {
+    let options = ProcessingOptions::new()
+        .amount_format(AmountFormat::MinorUnits)
+        .include_net(true);
+    let result = process_transactions_with_options(
+        test_directory().join("single_client_dispute.csv"),
+        &options,
+    )
+    .unwrap();
+
+    // Amounts are scaled by 10^4
+    let expected = "client,available,held,total,locked,net\n\
+    1,1000000,200000,1200000,false,800000\n";
+    assert_eq!(result.summary, expected)
+}
+
+#[test]
+fn process_transactions_no_transactions_with_net_test() {
+    let options = ProcessingOptions::new().include_net(true);
+    let result =
+        process_transactions_with_options(test_directory().join("no_transactions.csv"), &options)
+            .unwrap();
+
+    let expected = "client,available,held,total,locked,net";
+    assert_eq!(result.summary, expected)
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn process_transactions_should_stop_after_max_rejections_test() {
+    let options = ProcessingOptions::new().max_rejections(2);
+    let result = process_transactions_with_options(
+        test_directory().join("invalid_transactions.csv"),
+        &options,
+    )
+    .unwrap();
+
+    // The deposit at the end of the file is not processed
+    let expected = "client,available,held,total,locked\n\
+    1,10.0,0.0,10.0,false\n\
+    2,0.0,0.0,0.0,false\n";
+    assert_eq!(result.summary, expected);
+    assert_eq!(result.rejections.len(), 2);
+    assert!(result.truncated);
+}
+
+#[test]
+fn process_transactions_should_report_positions_of_rejections_test() {
+    let result = process_transactions_with_options(
+        test_directory().join("invalid_transactions.csv"),
+        &ProcessingOptions::default(),
+    )
+    .unwrap();
+
+    let positions: Vec<(u64, u64)> = result
+        .rejections
+        .iter()
+        .map(|rejection| (rejection.line, rejection.byte))
+        .collect();
+    assert_eq!(positions, vec![(3, 45), (4, 68), (5, 82), (6, 96)]);
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn process_transactions_below_max_rejections_should_not_stop_test() {
+    let options = ProcessingOptions::new().max_rejections(5);
+    let result = process_transactions_with_options(
+        test_directory().join("invalid_transactions.csv"),
+        &options,
+    )
+    .unwrap();
+
+    let expected = "client,available,held,total,locked\n\
+    1,110.0,0.0,110.0,false\n\
+    2,0.0,0.0,0.0,false\n";
+    assert_eq!(result.summary, expected);
+    assert_eq!(result.rejections.len(), 4);
+    assert!(!result.truncated);
+}
+
+#[test]
+fn process_transactions_with_comma_decimal_separator_test() {
+    let options = ProcessingOptions::new().decimal_separator(DecimalSeparator::Comma);
+    let result = process_transactions_with_options(
+        test_directory().join("comma_decimal_separator.csv"),
+        &options,
+    )
+    .unwrap();
+
+    let expected = "client,available,held,total,locked\n\
+    1,-2.25,12.5,10.25,false\n";
+    assert_eq!(result.summary, expected)
+}
+
+#[test]
+fn process_transactions_with_comma_decimal_separator_should_fail_by_default_test() {
+    let err =
+        process_transactions(test_directory().join("comma_decimal_separator.csv")).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("uses ',' as decimal separator, while '.' is expected"));
+}
+
+#[test]
+fn process_transactions_with_unquoted_comma_decimal_amount_should_fail_test() {
+    let input = "type,client,tx,amount\n\
+    deposit,1,1,12,50\n";
+    for separator in [DecimalSeparator::Comma, DecimalSeparator::Dot] {
+        let options = ProcessingOptions::new().decimal_separator(separator);
+        let err = process_transactions_from_reader(input.as_bytes(), &options).unwrap_err();
+
+        assert_eq!(
+            err.root_cause().to_string(),
+            "Record at line 2 has 5 fields while the header has 4, amounts with ',' decimal separator have to be quoted, e.g. \"12,50\""
+        );
+    }
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn process_transactions_with_client_map_test() {
+    let map = HashMap::from([(1, 100), (2, 100)]);
+    let result =
+        process_transactions_with_client_map(test_directory().join("clients_to_merge.csv"), &map)
+            .unwrap();
+
+    // Withdrawal of client 2 and dispute of client 1 use the founds of both clients
+    let expected = "client,available,held,total,locked\n\
+    3,1.0,0.0,1.0,false\n\
+    100,-2.0,5.0,3.0,false\n";
+    assert_eq!(result.summary, expected);
+    assert!(result.rejections.is_empty());
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn process_transactions_filtered_odd_clients_test() {
+    let result = process_transactions_filtered(
+        test_directory().join("multiple_users_all_types_of_transactions.csv"),
+        |client| client % 2 == 1,
+    )
+    .unwrap();
+
+    let expected = "client,available,held,total,locked\n\
+    1,20.0,0.0,20.0,false\n\
+    3,-300.0,0.0,-300.0,true\n";
+    assert_eq!(result.summary, expected);
+    // Skipped rows are not rejections
+    assert_eq!(result.rejections.len(), 2);
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn process_transactions_freeze_and_unfreeze_test() {
+    let result = process_transactions_str(
+        "type, client, tx, amount\n\
+        deposit, 1, 1, 10.0\n\
+        freeze, 1, 0,\n\
+        deposit, 1, 2, 10.0\n\
+        freeze, 2, 0,\n\
+        unfreeze, 1, 0,\n\
+        deposit, 1, 3, 5.0\n",
+    )
+    .unwrap();
+
+    let expected = "client,available,held,total,locked\n\
+    1,15.0,0.0,15.0,false\n\
+    2,0.0,0.0,0.0,true\n";
+    assert_eq!(result, expected)
+}
+
+const FRACTIONAL_AMOUNTS: &str = "type, client, tx, amount\n\
+    deposit, 1, 1, 130.0\n\
+    deposit, 2, 2, 1.23456\n\
+    dispute, 2, 2\n";
+
+#[test]
+fn process_transactions_with_2_fixed_decimal_places_test() {
+    let options = ProcessingOptions::new().fixed_decimal_places(2);
+    let result = process_transactions_from_reader(FRACTIONAL_AMOUNTS.as_bytes(), &options).unwrap();
+
+    let expected = "client,available,held,total,locked\n\
+    1,130.00,0.00,130.00,false\n\
+    2,0.00,1.23,1.23,false\n";
+    assert_eq!(result.summary, expected)
+}
+
+#[test]
+fn process_transactions_with_4_fixed_decimal_places_test() {
+    let options = ProcessingOptions::new()
+        .fixed_decimal_places(4)
+        .include_net(true);
+    let result = process_transactions_from_reader(FRACTIONAL_AMOUNTS.as_bytes(), &options).unwrap();
+
+    let expected = "client,available,held,total,locked,net\n\
+    1,130.0000,0.0000,130.0000,false,130.0000\n\
+    2,0.0000,1.2346,1.2346,false,-1.2346\n";
+    assert_eq!(result.summary, expected)
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn process_reader_with_comment_character_test() {
+    let input = "type,client,tx,amount\n\
+        # deposits\n\
+        deposit,1,1,10.0\n\
+        deposit,1,2,5.0\n\
+        # partial withdrawal\n\
+        withdrawal,1,3,2.5\n";
+    let reader = ReaderBuilder::new()
+        .comment(Some(b'#'))
+        .from_reader(input.as_bytes());
+    let result = process_reader(reader).unwrap();
+
+    let expected = "client,available,held,total,locked\n\
+    1,12.5,0.0,12.5,false\n";
+    assert_eq!(result, expected)
+}
+
+#[test]
+fn run_should_return_summary_and_rejections_test() {
+    let transaction = |transaction_type, client, tx, value: Option<&str>| Transaction {
+        transaction_type,
+        client,
+        transaction_id: tx,
+        amount: value.map(amount),
+        timestamp: None,
+    };
+    let (summary, rejections) = run(vec![
+        transaction(TransactionType::Deposit, 2, 1, Some("10.0")),
+        transaction(TransactionType::Withdrawal, 2, 2, Some("20.0")),
+        transaction(TransactionType::Deposit, 1, 3, Some("5.0")),
+        transaction(TransactionType::Dispute, 1, 3, None),
+        transaction(TransactionType::Resolve, 2, 1, None),
+    ]);
+
+    assert_eq!(
+        summary,
+        vec![
+            ClientSummary {
+                client: 1,
+                available: amount("0.0"),
+                held: amount("5.0"),
+                total: amount("5.0"),
+                locked: false,
+            },
+            ClientSummary {
+                client: 2,
+                available: amount("10.0"),
+                held: amount("0.0"),
+                total: amount("10.0"),
+                locked: false,
+            },
+        ]
+    );
+    assert_eq!(
+        rejections,
+        vec![
+            (1, TransactionProcessError::NotEnoughFoundsAvailable),
+            (4, TransactionProcessError::TransactionNotUnderDispute),
+        ]
+    );
+}
+
+#[test]
+fn process_transactions_with_too_long_record_should_fail_test() {
+    let input = format!(
+        "type, client, tx, amount\n\
+        deposit, 1, 1, 10.0\n\
+        deposit, 1, 2, 1{}\n",
+        "0".repeat(1000)
+    );
+    let options = ProcessingOptions::new().max_record_bytes(100);
+    let err = process_transactions_from_reader(input.as_bytes(), &options).unwrap_err();
+
+    assert_eq!(
+        err.root_cause().to_string(),
+        "Record at line 3 exceeds the limit of 100 bytes"
+    );
+}
+
+#[test]
+fn process_transactions_with_endless_record_should_fail_test() {
+    let input = "type, client, tx, amount\ndeposit, 1, 1, 1"
+        .as_bytes()
+        .chain(std::io::repeat(b'0'));
+    let options = ProcessingOptions::new().max_record_bytes(1024);
+    let err = process_transactions_from_reader(input, &options).unwrap_err();
+
+    assert_eq!(
+        err.root_cause().to_string(),
+        "Record at line 2 exceeds the limit of 1024 bytes"
+    );
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn process_transactions_with_currency_test() {
+    let input = "type, client, tx, amount\n\
+        deposit, 1, 1, 10.0\n\
+        deposit, 2, 2, 20.0\n\
+        deposit, 3, 3, 30.0\n";
+    let options = ProcessingOptions::new()
+        .currency("USD")
+        .client_currencies(HashMap::from([(2, "EUR".to_string())]));
+    let result = process_transactions_from_reader(input.as_bytes(), &options).unwrap();
+
+    let mut lines = result.summary.lines().collect::<Vec<_>>();
+    lines[1..].sort();
+    assert_eq!(
+        lines,
+        vec![
+            "client,available,held,total,currency,locked",
+            "1,10.0,0.0,10.0,USD,false",
+            "2,20.0,0.0,20.0,EUR,false",
+            "3,30.0,0.0,30.0,USD,false",
+        ]
+    );
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn process_transactions_as_ndjson_with_currency_test() {
+    let input = "type, client, tx, amount\n\
+        deposit, 1, 1, 10.0\n\
+        deposit, 2, 2, 20.0\n";
+    let options = ProcessingOptions::new()
+        .output_format(OutputFormat::Ndjson)
+        .client_currencies(HashMap::from([(2, "EUR".to_string())]));
+    let result = process_transactions_from_reader(input.as_bytes(), &options).unwrap();
+
+    let mut lines = result.summary.lines().collect::<Vec<_>>();
+    lines.sort();
+    assert_eq!(
+        lines,
+        vec![
+            "{\"client\":1,\"available\":10.0,\"held\":0.0,\"total\":10.0,\"currency\":\"\",\"locked\":false}",
+            "{\"client\":2,\"available\":20.0,\"held\":0.0,\"total\":20.0,\"currency\":\"EUR\",\"locked\":false}",
+        ]
+    );
+}
+
+#[test]
+fn process_with_driver_one_record_at_a_time_should_match_batch_test() {
+    let filename = test_directory().join("invalid_transactions.csv");
+    let options = ProcessingOptions::new();
+    let batch = process_transactions_with_options(&filename, &options).unwrap();
+
+    let mut driver = ProcessingDriver::new(fs::File::open(&filename).unwrap(), &options).unwrap();
+    let mut steps = 0;
+    while driver.step().unwrap() {
+        steps += 1;
+    }
+    let stepped = driver.finish().unwrap();
+
+    assert_eq!(
+        steps,
+        fs::read_to_string(&filename).unwrap().lines().count() - 1
+    );
+    assert_eq!(stepped.summary, batch.summary);
+    assert_eq!(stepped.rejections, batch.rejections);
+    assert!(!stepped.truncated);
+}
+
+#[test]
+fn process_transactions_with_empty_type_should_fail_test() {
+    let err = process_transactions_str("type, client, tx, amount\n , 1, 1, 10.0\n").unwrap_err();
+
+    assert!(format!("{err:#}").contains("Empty transaction type"));
+}
+
+#[test]
+fn process_transactions_with_unknown_type_should_fail_test() {
+    let err =
+        process_transactions_str("type, client, tx, amount\ndepsit, 1, 1, 10.0\n").unwrap_err();
+
+    let message = format!("{err:#}");
+    assert!(message.contains("Unknown transaction type \"depsit\""));
+    assert!(!message.contains("Empty transaction type"));
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn process_transactions_with_halt_on_lock_should_stop_after_chargeback_test() {
+    let input = "type, client, tx, amount\n\
+        deposit, 1, 1, 100.0\n\
+        deposit, 2, 2, 20.0\n\
+        dispute, 2, 2\n\
+        chargeback, 2, 2\n\
+        deposit, 1, 3, 50.0\n\
+        deposit, 3, 4, 10.0\n";
+    let options = ProcessingOptions::new().halt_on_lock(true);
+    let result = process_transactions_from_reader(input.as_bytes(), &options).unwrap();
+
+    let expected = "client,available,held,total,locked\n\
+    1,100.0,0.0,100.0,false\n\
+    2,0.0,0.0,0.0,true\n";
+    assert_eq!(result.summary, expected);
+    assert!(result.truncated);
+    assert_eq!(result.halted_by, Some(2));
+}
+
+#[test]
+fn process_transactions_without_halt_on_lock_should_process_whole_file_test() {
+    let result = process_transactions_with_rejections(
+        test_directory().join("single_client_dispute_chargeback.csv"),
+    )
+    .unwrap();
+
+    assert!(!result.truncated);
+    assert_eq!(result.halted_by, None);
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn process_transactions_dir_should_process_files_in_name_order_test() {
+    let dir = tempfile::tempdir().unwrap();
+    // The dispute of the second day refers to the deposit of the first day
+    fs::write(
+        dir.path().join("2024-01-02.csv"),
+        "type, client, tx, amount\ndispute, 1, 1,\nwithdrawal, 2, 3, 5.0\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("2024-01-01.csv"),
+        "type, client, tx, amount\ndeposit, 1, 1, 100.0\ndeposit, 2, 2, 20.0\n",
+    )
+    .unwrap();
+    fs::write(dir.path().join("notes.txt"), "not a transactions file").unwrap();
+
+    let result = process_transactions_dir(dir.path()).unwrap();
+
+    let expected = "client,available,held,total,locked\n\
+    1,0.0,100.0,100.0,false\n\
+    2,15.0,0.0,15.0,false\n";
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn grand_totals_should_match_sum_of_client_summaries_test() {
+    let mut reader = ReaderBuilder::new()
+        .trim(Trim::All)
+        .flexible(true)
+        .from_path(test_directory().join("multiple_users_all_types_of_transactions.csv"))
+        .unwrap();
+    let mut processor = TransactionsProcessor::default();
+    for transaction in reader.deserialize::<Transaction>() {
+        // Rejected transactions are ignored the same way as when processing the file
+        let _ = processor.process(&transaction.unwrap());
+    }
+
+    let summaries = processor.summary();
+    let totals = processor.grand_totals();
+
+    let sum = |value: fn(&ClientSummary) -> Amount| summaries.iter().map(value).sum::<Amount>();
+    let tolerance = amount("0.000000001");
+    assert!((totals.available - sum(|summary| summary.available)).abs() < tolerance);
+    assert!((totals.held - sum(|summary| summary.held)).abs() < tolerance);
+    assert!((totals.total - sum(|summary| summary.total)).abs() < tolerance);
+    assert_eq!(
+        totals.locked_count,
+        summaries.iter().filter(|summary| summary.locked).count()
+    );
+    assert_eq!(totals.locked_count, 2);
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn process_transactions_with_quoted_crlf_output_test() {
+    let options = ProcessingOptions::new()
+        .quote_style(QuoteStyle::Always)
+        .line_terminator(LineTerminator::CrLf);
+    let result = process_transactions_with_options(
+        test_directory().join("single_client_dispute.csv"),
+        &options,
+    )
+    .unwrap();
+
+    assert_eq!(
+        result.summary.as_bytes(),
+        b"\"client\",\"available\",\"held\",\"total\",\"locked\"\r\n\
+        \"1\",\"100.0\",\"20.0\",\"120.0\",\"false\"\r\n"
+    );
+}
+
+#[test]
+fn process_transactions_no_transactions_with_quoted_output_test() {
+    let options = ProcessingOptions::new().quote_style(QuoteStyle::Always);
+    let result =
+        process_transactions_with_options(test_directory().join("no_transactions.csv"), &options)
+            .unwrap();
+
+    assert_eq!(
+        result.summary,
+        "\"client\",\"available\",\"held\",\"total\",\"locked\""
+    );
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn process_transactions_with_checkpoints_test() {
+    let mut checkpoints = vec![];
+    let result = process_transactions_with_checkpoints(
+        test_directory().join("single_client_dispute_chargeback.csv"),
+        &ProcessingOptions::new(),
+        2,
+        &mut checkpoints,
+    )
+    .unwrap();
+
+    // 4 records give checkpoints after the second and the fourth record
+    let expected_checkpoints = "# checkpoint 1 after 2 records\n\
+    client,available,held,total,locked\n\
+    1,120.0,0.0,120.0,false\n\
+    # checkpoint 2 after 4 records\n\
+    client,available,held,total,locked\n\
+    1,100.0,0.0,100.0,true\n";
+    assert_eq!(
+        String::from_utf8(checkpoints).unwrap(),
+        expected_checkpoints
+    );
+    let expected =
+        fs::read_to_string(test_directory().join("expected_single_client_dispute_chargeback.csv"))
+            .unwrap();
+    assert_eq!(result.summary, expected);
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn process_transactions_with_trailing_empty_lines_test() {
+    let input = "type, client, tx, amount\n\
+        deposit, 1, 1, 100.0\n\
+        deposit, 1, 2, 20.0\n\
+        dispute, 1, 2\n\
+        ,,,\n\
+        \n   \n\
+        , , ,\n";
+    let result =
+        process_transactions_from_reader(input.as_bytes(), &ProcessingOptions::new()).unwrap();
+
+    let expected =
+        fs::read_to_string(test_directory().join("expected_single_client_dispute.csv")).unwrap();
+    assert_eq!(result.summary, expected);
+    assert!(result.rejections.is_empty());
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn process_transactions_time_ordered_test() {
+    let result =
+        process_transactions_time_ordered(test_directory().join("out_of_order_timestamps.csv"))
+            .unwrap();
+
+    // In order of timestamps: deposits, withdrawal, dispute and chargeback of the second deposit
+    let expected = "client,available,held,total,locked\n\
+    1,70.0,0.0,70.0,true\n\
+    2,5.0,0.0,5.0,false\n";
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn process_transactions_time_ordered_without_timestamp_should_fail_test() {
+    let err =
+        process_transactions_time_ordered(test_directory().join("single_client_deposits.csv"))
+            .unwrap_err();
+
+    assert_eq!(err.to_string(), "Transaction at line 2 has no timestamp");
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn process_transactions_active_only_test() {
+    let input = "type, client, tx, amount\n\
+        deposit, 1, 1, 10.0\n\
+        withdrawal, 2, 2, 10.0\n";
+    let options = ProcessingOptions::new().active_only(true);
+    let result = process_transactions_from_reader(input.as_bytes(), &options).unwrap();
+
+    let expected = "client,available,held,total,locked\n\
+    1,10.0,0.0,10.0,false\n";
+    assert_eq!(result.summary, expected);
+    assert_eq!(result.rejections.len(), 1);
+}
+
+#[test]
+fn process_transactions_rejections_by_client_test() {
+    let input = "type, client, tx, amount\n\
+        deposit, 1, 1, 10.0\n\
+        withdrawal, 1, 2, 20.0\n\
+        dispute, 2, 3\n\
+        deposit, 2, 4, 5.0\n\
+        resolve, 1, 1\n\
+        withdrawal, 2, 5, 6.0\n";
+    let result =
+        process_transactions_from_reader(input.as_bytes(), &ProcessingOptions::new()).unwrap();
+
+    assert_eq!(
+        result.rejections_by_client(),
+        HashMap::from([
+            (
+                1,
+                vec![
+                    TransactionProcessError::NotEnoughFoundsAvailable,
+                    TransactionProcessError::TransactionNotUnderDispute
+                ]
+            ),
+            (
+                2,
+                vec![
+                    TransactionProcessError::TransactionNotFound,
+                    TransactionProcessError::NotEnoughFoundsAvailable
+                ]
+            ),
+        ])
+    );
+}
+
+#[test]
+fn process_transactions_as_table_test() {
+    let options = ProcessingOptions::new().output_format(OutputFormat::Table);
+    let result = process_transactions_with_options(
+        test_directory().join("multiple_users_all_types_of_transactions.csv"),
+        &options,
+    )
+    .unwrap();
+
+    let expected = [
+        "client  available      held      total  locked",
+        "------  ---------  --------  ---------  ------",
+        "     1    20.0000    0.0000    20.0000   false",
+        "     2    50.0000  100.0000   150.0000   false",
+        "     3  -300.0000    0.0000  -300.0000    true",
+        "     4   400.0000    0.0000   400.0000    true",
+    ];
+    assert_eq!(
+        result.summary,
+        expected.map(|line| format!("{line}\n")).concat()
+    );
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn integer_like_amounts_should_give_the_same_summary_test() {
+    let summaries = ["100", "100.0", "100.0000"].map(|amount| {
+        let input = format!(
+            "type, client, tx, amount\n\
+            deposit, 1, 1, {amount}\n\
+            deposit, 1, 2, {amount}\n\
+            dispute, 1, 2\n"
+        );
+        process_transactions_str(&input).unwrap()
+    });
+
+    let expected = "client,available,held,total,locked\n\
+    1,100.0,100.0,200.0,false\n";
+    assert_eq!(summaries, [expected; 3]);
+}
+
+#[test]
+fn integer_like_amounts_should_give_the_same_client_summary_test() {
+    let summaries = ["100", "100.0", "100.0000"].map(|amount| {
+        let (summaries, rejections) = run(vec![Transaction {
+            transaction_type: TransactionType::Deposit,
+            client: 1,
+            transaction_id: 1,
+            amount: Some(amount.parse().unwrap()),
+            timestamp: None,
+        }]);
+        assert!(rejections.is_empty());
+        summaries
+    });
+
+    assert!(summaries.iter().all(|summary| summary == &summaries[0]));
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn process_transactions_from_stdin_like_buffer_test() {
+    let input = fs::read(test_directory().join("single_client_dispute.csv")).unwrap();
+    let stdin = std::io::BufReader::new(std::io::Cursor::new(input));
+
+    let result = process_transactions_from_reader(stdin, &ProcessingOptions::new()).unwrap();
+
+    let expected =
+        fs::read_to_string(test_directory().join("expected_single_client_dispute.csv")).unwrap();
+    assert_eq!(result.summary, expected);
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn process_transactions_with_progress_test() {
+    let input =
+        fs::read(test_directory().join("multiple_users_all_types_of_transactions.csv")).unwrap();
+    let mut progress = vec![];
+
+    let result = process_transactions_from_reader_with_progress(
+        input.as_slice(),
+        &ProcessingOptions::new(),
+        10,
+        |records| progress.push(records),
+    )
+    .unwrap();
+
+    // 37 records in the file
+    assert_eq!(progress, vec![10, 20, 30]);
+    let expected = fs::read_to_string(
+        test_directory().join("expected_multiple_users_all_types_of_transactions.csv"),
+    )
+    .unwrap();
+    assert_eq!(result.summary, expected);
+}
+
+const UNKNOWN_TYPE_INPUT: &str = "type, client, tx, amount\n\
+    deposit, 1, 1, 100.0\n\
+    transfer, 1, 2, 50.0\n\
+    withdrawal, 1, 3, 30.0\n";
+
+#[test]
+fn process_transactions_with_unknown_type_should_abort_by_default_test() {
+    let options = ProcessingOptions::new();
+    let err =
+        process_transactions_from_reader(UNKNOWN_TYPE_INPUT.as_bytes(), &options).unwrap_err();
+
+    assert!(format!("{err:#}").contains("Unknown transaction type \"transfer\""));
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn process_transactions_with_unknown_type_should_skip_row_test() {
+    let options = ProcessingOptions::new().on_unknown_type(UnknownTypePolicy::Skip);
+    let result = process_transactions_from_reader(UNKNOWN_TYPE_INPUT.as_bytes(), &options).unwrap();
+
+    let expected = "client,available,held,total,locked\n\
+    1,70.0,0.0,70.0,false\n";
+    assert_eq!(result.summary, expected);
+    assert!(result.rejections.is_empty());
+}
+
+#[test]
+fn process_headerless_transactions_should_match_output_with_header_test() {
+    let path = test_directory().join("multiple_users_all_types_of_transactions.csv");
+    let input = fs::read_to_string(&path).unwrap();
+    let (_, records) = input.split_once('\n').unwrap();
+
+    let options = ProcessingOptions::new().headerless(true);
+    let result = process_transactions_from_reader(records.as_bytes(), &options).unwrap();
+
+    assert_eq!(result.summary, process_transactions(path).unwrap());
+}
+
+#[test]
+fn process_transactions_locked_account_rejections_test() {
+    let report = process_transactions_with_rejections(
+        test_directory().join("multiple_users_all_types_of_transactions.csv"),
+    )
+    .unwrap();
+
+    let skipped = report
+        .locked_account_rejections()
+        .map(|transaction| {
+            (
+                transaction.client,
+                transaction.transaction_type,
+                transaction.transaction_id,
+            )
+        })
+        .collect::<Vec<_>>();
+    // Transactions after the chargebacks of clients 3 and 4, other rejections are not caused by the lock
+    assert_eq!(
+        skipped,
+        vec![
+            (3, TransactionType::Deposit, 34),
+            (4, TransactionType::Deposit, 44),
+            (4, TransactionType::Withdrawal, 44),
+            (4, TransactionType::Resolve, 41),
+            (4, TransactionType::Dispute, 41),
+            (4, TransactionType::Resolve, 41),
+        ]
+    );
+    assert!(report.rejections.len() > skipped.len());
+}
+
+#[test]
+fn process_transactions_raw_rejections_should_match_input_rows_test() {
+    let input = "tx,type,client,amount\r\n\
+    1,deposit,1,10.000\r\n\
+    2,withdrawal,1,50.50\r\n\
+    1,deposit,1,5.5\r\n";
+    let options = ProcessingOptions::new().keep_raw_rejections(true);
+    let report = process_transactions_from_reader(input.as_bytes(), &options).unwrap();
+
+    let mut out = vec![];
+    write_raw_rejections(&report.rejections, &mut out).unwrap();
+
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "2,withdrawal,1,50.50\n\
+        1,deposit,1,5.5\n"
+    );
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn process_transactions_with_empty_amount_cell_test() {
+    let input = "type,client,tx,amount\n\
+    deposit,1,1,10.0\n\
+    dispute,1,1,\n\
+    deposit,1,2,\n";
+    let report =
+        process_transactions_from_reader(input.as_bytes(), &ProcessingOptions::new()).unwrap();
+
+    assert_eq!(
+        report.summary,
+        "client,available,held,total,locked\n1,0.0,10.0,10.0,false\n"
+    );
+    assert_eq!(report.rejections.len(), 1);
+    assert_eq!(report.rejections[0].transaction.transaction_id, 2);
+    assert_eq!(
+        report.rejections[0].error,
+        TransactionProcessError::MissingAmountValue
+    );
+}
+
+#[test]
+fn process_transactions_raw_rejections_should_keep_whitespace_of_input_test() {
+    let path = test_directory().join("multiple_users_all_types_of_transactions.csv");
+    let options = ProcessingOptions::new().keep_raw_rejections(true);
+    let report = process_transactions_with_options(&path, &options).unwrap();
+
+    let mut out = vec![];
+    write_raw_rejections(&report.rejections, &mut out).unwrap();
+
+    let input = std::fs::read_to_string(&path).unwrap();
+    let lines = input.lines().collect::<Vec<_>>();
+    let expected = report
+        .rejections
+        .iter()
+        .map(|rejection| format!("{}\n", lines[rejection.line as usize - 1]))
+        .collect::<String>();
+    assert!(
+        expected.starts_with("withdrawal, 1, 4, 30.0\n"),
+        "{expected}"
+    );
+    assert_eq!(String::from_utf8(out).unwrap(), expected);
+    assert_eq!(
+        report.summary,
+        process_transactions(&path).unwrap(),
+        "Summary should not depend on keeping raw rejections"
+    );
+}
+
+#[test]
+fn write_raw_rejections_without_raw_records_should_fail_test() {
+    let input = "type,client,tx,amount\nwithdrawal,1,1,5.0\n";
+    let report =
+        process_transactions_from_reader(input.as_bytes(), &ProcessingOptions::new()).unwrap();
+
+    assert!(write_raw_rejections(&report.rejections, vec![]).is_err());
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn process_transactions_with_limit_test() {
+    let options = ProcessingOptions::new().limit(2);
+    let report = process_transactions_with_options(
+        test_directory().join("single_client_deposits_and_withdrawals.csv"),
+        &options,
+    )
+    .unwrap();
+
+    let expected = "client,available,held,total,locked\n\
+    1,70.0,0.0,70.0,false\n";
+    assert_eq!(report.summary, expected);
+    assert!(report.truncated);
+}
+
+#[test]
+fn process_transactions_with_limit_over_number_of_records_test() {
+    let path = test_directory().join("single_client_deposits_and_withdrawals.csv");
+    let report =
+        process_transactions_with_options(&path, &ProcessingOptions::new().limit(5)).unwrap();
+
+    assert_eq!(report.summary, process_transactions(&path).unwrap());
+    assert!(!report.truncated);
+}
+
+#[test]
+fn process_transactions_with_limit_equal_to_number_of_records_should_not_be_truncated_test() {
+    let path = test_directory().join("single_client_deposits_and_withdrawals.csv");
+    let report =
+        process_transactions_with_options(&path, &ProcessingOptions::new().limit(4)).unwrap();
+
+    assert_eq!(report.summary, process_transactions(&path).unwrap());
+    assert!(!report.truncated);
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn process_transactions_with_limit_should_not_parse_records_after_it_test() {
+    let input = b"type,client,tx,amount\n\
+    deposit,1,1,10.0\n\
+    deposit,1,2,5.0\n\
+    deposit,1,3,\xff\n";
+    let options = ProcessingOptions::new().limit(2);
+    let report = process_transactions_from_reader(&input[..], &options).unwrap();
+
+    assert_eq!(
+        report.summary,
+        "client,available,held,total,locked\n1,15.0,0.0,15.0,false\n"
+    );
+    assert!(report.truncated);
+    assert!(process_transactions_from_reader(&input[..], &ProcessingOptions::new()).is_err());
+}
+
+#[test]
+fn try_process_transactions_missing_file_should_fail_with_io_error_test() {
+    let err = try_process_transactions(test_directory().join("missing.csv")).unwrap_err();
+
+    assert!(
+        matches!(&err, ProcessFileError::Io(err) if err.kind() == std::io::ErrorKind::NotFound),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn try_process_transactions_malformed_record_should_fail_with_parse_error_test() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("malformed.csv");
+    fs::write(
+        &path,
+        "type, client, tx, amount\ndeposit, 1, 1, 10.0\ndeposit, abc, 2, 5.0\n",
+    )
+    .unwrap();
+
+    let err = try_process_transactions(&path).unwrap_err();
+
+    assert!(
+        matches!(err, ProcessFileError::Parse { line: 3, .. }),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn try_process_transactions_invalid_utf8_should_fail_with_encoding_error_test() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("latin1.csv");
+    fs::write(
+        &path,
+        b"type, client, tx, amount\ndeposit, 1, 1, 10.0\xff\n",
+    )
+    .unwrap();
+
+    let err = try_process_transactions(&path).unwrap_err();
+
+    assert!(
+        matches!(err, ProcessFileError::Encoding { line: 2 }),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn try_process_transactions_should_match_process_transactions_test() {
+    let path = test_directory().join("multiple_users_all_types_of_transactions.csv");
+
+    assert_eq!(
+        try_process_transactions(&path).unwrap(),
+        process_transactions(&path).unwrap()
+    );
+}
+
+#[cfg(not(feature = "decimal"))]
+#[test]
+fn process_transactions_drop_zero_held_should_omit_held_column_test() {
+    let options = ProcessingOptions::new().drop_zero_held(true);
+    let report = process_transactions_with_options(
+        test_directory().join("single_client_deposits_and_withdrawals.csv"),
+        &options,
+    )
+    .unwrap();
+
+    let expected = "client,available,total,locked\n\
+    1,40.0,40.0,false\n";
+    assert_eq!(report.summary, expected);
+}
+
+#[test]
+fn process_transactions_drop_zero_held_should_keep_non_zero_held_column_test() {
+    let path = test_directory().join("multiple_users_all_types_of_transactions.csv");
+    let options = ProcessingOptions::new().drop_zero_held(true);
+    let report = process_transactions_with_options(&path, &options).unwrap();
+
+    assert_eq!(report.summary, process_transactions(&path).unwrap());
+    assert!(report
+        .summary
+        .starts_with("client,available,held,total,locked\n"));
+}